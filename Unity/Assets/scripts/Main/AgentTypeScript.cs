@@ -10,7 +10,14 @@ namespace Main
 			Random = 1,
 			Rollout = 2,
 			Dijkstra = 3,
-			QLearning = 4
+			QLearning = 4,
+			Mcts = 5,
+			Minimax = 6,
+			HeuristicEasy = 7,
+			HeuristicMedium = 8,
+			HeuristicHard = 9,
+			LinearQLearning = 10,
+			Dqn = 11
 		}
 
 		public static AgentTypeScript Instance;
@@ -19,6 +26,8 @@ namespace Main
 		public int nbFrames2 = 1000;
 		public int nbSim1 = 3;
 		public int nbSim2 = 3;
+		public double timeBudget1 = 0.0; // Search time in seconds of the MCTS and minimax agents, 0 to search `nbSim` iterations
+		public double timeBudget2 = 0.0;
 		public bool turbo;
 		
 		[HideInInspector]
@@ -33,6 +42,8 @@ namespace Main
 			nbFrames2 = 1000;
 			nbSim1 = 3;
 			nbSim2 = 3;
+			timeBudget1 = 0.0;
+			timeBudget2 = 0.0;
 			turbo = false;
 		}
 	}
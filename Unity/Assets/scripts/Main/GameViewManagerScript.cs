@@ -39,10 +39,12 @@ namespace Main
 			public double p1_x;
 			public double p1_y;
 			public sbyte p1_score;
+			public sbyte p1_side;
 
 			public double p2_x;
 			public double p2_y;
 			public sbyte p2_score;
+			public sbyte p2_side;
 
 			public double zbee_x;
 			public double zbee_y;
@@ -51,15 +53,26 @@ namespace Main
 			public double time;
 
 			public sbyte State;
+
+			public sbyte p1_sets;
+			public sbyte p2_sets;
+			public sbyte set_number;
+			public sbyte round_number;
+			public sbyte tie_break;
+
+			public double zbee_height;
+			public double zbee_speed;
+			public sbyte rally;
+			public double hold_time_left;
 		}
 
 		private ManagedState mState;
 
 		[DllImport("rustjammers_engine")]
-		private static extern void send_type_p1(IntPtr gameEngine, sbyte type1, double frames, int sim);
+		private static extern void send_type_p1(IntPtr gameEngine, sbyte type1, double frames, int sim, double timeBudget);
 
 		[DllImport("rustjammers_engine")]
-		private static extern void send_type_p2(IntPtr gameEngine, sbyte type2, double frames, int sim);
+		private static extern void send_type_p2(IntPtr gameEngine, sbyte type2, double frames, int sim, double timeBudget);
 		
 		[DllImport("rustjammers_engine")]
 		private static extern IntPtr initialize();
@@ -87,13 +100,15 @@ namespace Main
 			Left = 4,
 			Right = 8,
 			Throw = 16,
+			Lob = 32,
 		}
 
 		private enum StateOfGame
 		{
 			Start = 0,
 			Playing = 1,
-			End = 2
+			End = 2,
+			SetEnd = 3
 		}
 
 		private void Start()
@@ -105,8 +120,8 @@ namespace Main
 			reset(this.currentGameEngine);
 			this.mState = new ManagedState();
 		
-			send_type_p1(this.currentGameEngine, (sbyte) this.agentTypeManager.Types[0], AgentTypeScript.Instance.nbFrames1, AgentTypeScript.Instance.nbSim1);
-			send_type_p2(this.currentGameEngine, (sbyte) this.agentTypeManager.Types[1], AgentTypeScript.Instance.nbFrames2, AgentTypeScript.Instance.nbSim2);
+			send_type_p1(this.currentGameEngine, (sbyte) this.agentTypeManager.Types[0], AgentTypeScript.Instance.nbFrames1, AgentTypeScript.Instance.nbSim1, AgentTypeScript.Instance.timeBudget1);
+			send_type_p2(this.currentGameEngine, (sbyte) this.agentTypeManager.Types[1], AgentTypeScript.Instance.nbFrames2, AgentTypeScript.Instance.nbSim2, AgentTypeScript.Instance.timeBudget2);
 		}
 
 		private void CollectInput(int index)
@@ -135,6 +150,11 @@ namespace Main
 			{
 				this.inputs[index] |= HumanInput.Throw;
 			}
+
+			if (Input.GetButtonDown("P" + inputManagerIndex + "Lob"))
+			{
+				this.inputs[index] |= HumanInput.Lob;
+			}
 		}
 
 		private void Update()
@@ -167,7 +187,7 @@ namespace Main
 
 				epoch(this.currentGameEngine, (sbyte) this.inputs[0], (sbyte) this.inputs[1]);
 				this.mState = get_state(this.currentGameEngine);
-				if(mState.time>1.0 && !endScreenManager.isActived){
+				if(this.mState.State != (sbyte) StateOfGame.End && !endScreenManager.isActived){
 					if (this.mState.p1_score < 10)
 					{
 						this.p1Score.text = "0" + this.mState.p1_score;
@@ -212,11 +232,11 @@ namespace Main
 				}
 				if (!this.frisbeeHeld)
 				{
-					this.frisbeeTransform.position = new Vector3((float) this.mState.zbee_x, 1.3f, (float) this.mState.zbee_y);
+					this.frisbeeTransform.position = new Vector3((float) this.mState.zbee_x, 1.3f + (float) this.mState.zbee_height, (float) this.mState.zbee_y);
 				}
-				if (this.mState.State != (sbyte) StateOfGame.End && mState.time>1.0 )
+				if (this.mState.State != (sbyte) StateOfGame.End)
 				{
-					var roundedTime = Mathf.RoundToInt((float) this.mState.time);
+					var roundedTime = Mathf.Max(0, Mathf.RoundToInt((float) this.mState.time));
 					if (roundedTime < 10)
 					{
 						this.SetTimerText("0" + roundedTime);
@@ -231,7 +251,7 @@ namespace Main
 					this.endScreenManager.Enable();
 					if (!block)
 					{
-						this.endScreenManager.SetScore((int) this.mState.p1_score, (int) this.mState.p2_score);
+						this.endScreenManager.SetScore((int) this.mState.p1_sets, (int) this.mState.p2_sets);
 						block = true;
 					}
 					if (AgentTypeScript.Instance.turbo)
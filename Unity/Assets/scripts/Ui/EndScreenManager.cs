@@ -40,6 +40,7 @@ namespace Ui
 			this.scores[index].StateText.text = state;
 		}
 
+		// Displays the number of sets won by each player
 		public void SetScore(int p1, int p2)
 		{
 			var state1 = WinState.Draw;
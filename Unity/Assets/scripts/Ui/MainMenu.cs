@@ -90,7 +90,7 @@ namespace Ui
 		{
 			for (var i = 0; i < this.agentDropdowns.Length; i++)
 			{
-				this.agentTypeManager.Types[i] = (AgentTypeScript.AgentType) this.agentDropdowns[i].value;
+				this.agentTypeManager.Types[i] = this.AgentTypeAt(i, this.agentDropdowns[i].value);
 			}
 			SceneManager.LoadSceneAsync(this.gameScene, LoadSceneMode.Single);
 		}
@@ -99,7 +99,7 @@ namespace Ui
 		{
 			for (var i = 0; i < this.agentDropdowns.Length; i++)
 			{
-				this.agentTypeManager.Types[i] = (AgentTypeScript.AgentType) this.agentDropdowns[i].value;
+				this.agentTypeManager.Types[i] = this.AgentTypeAt(i, this.agentDropdowns[i].value);
 			}
 			SceneManager.LoadSceneAsync(this.gameScene, LoadSceneMode.Single);
 			AgentTypeScript.Instance.turbo = true;
@@ -114,10 +114,24 @@ namespace Ui
 #endif
 		}
 
+		// Options are not in the order of the enum since Q-learning is only offered to player 1
+		private AgentTypeScript.AgentType AgentTypeAt(int dropdown, Int32 val)
+		{
+			var name = this.agentDropdowns[dropdown].options[val].text;
+			return (AgentTypeScript.AgentType) Enum.Parse(typeof(AgentTypeScript.AgentType), name);
+		}
+
+		private static bool IsSearchAgent(AgentTypeScript.AgentType type)
+		{
+			return type == AgentTypeScript.AgentType.Rollout || type == AgentTypeScript.AgentType.Mcts ||
+			       type == AgentTypeScript.AgentType.Minimax;
+		}
+
 		public void OnP1AgentTypeChanged(Int32 val)
 		{
-			var human = val == (Int32) AgentTypeScript.AgentType.Human;
-			var rollout = val == (Int32) AgentTypeScript.AgentType.Rollout;
+			var type = this.AgentTypeAt(0, val);
+			var human = type == AgentTypeScript.AgentType.Human;
+			var rollout = IsSearchAgent(type);
 			this.agentKbds[0].SetActive(human);
 			this.inputFields[0].gameObject.SetActive(rollout);
 			this.inputFields[2].gameObject.SetActive(rollout);
@@ -125,8 +139,9 @@ namespace Ui
 
 		public void OnP2AgentTypeChanged(Int32 val)
 		{
-			var human = val == (Int32) AgentTypeScript.AgentType.Human;
-			var rollout = val == (Int32) AgentTypeScript.AgentType.Rollout;
+			var type = this.AgentTypeAt(1, val);
+			var human = type == AgentTypeScript.AgentType.Human;
+			var rollout = IsSearchAgent(type);
 			this.agentKbds[1].SetActive(human);
 			this.inputFields[1].gameObject.SetActive(rollout);
 			this.inputFields[3].gameObject.SetActive(rollout);
@@ -1,27 +1,48 @@
 use vector2::Vector2;
 use player::PlayerSide;
-use frisbee::ThrowDirection;
-use game_engine::{ GameEngine, StateOfGame };
+use frisbee::{ ThrowDirection, CurveDirection };
+use game_engine::{ GameEngine, StateOfGame, EngineRng };
 
 use rand::Rng;
-use std::collections::HashMap;
 
-#[derive(PartialEq, Clone, Copy, Debug)]
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum AgentType {
     HumanPlayer = 0,
     Random,
     RandomRollout,
     Dijkstra,
     TabularQLearning, 
+    Mcts,
+    Minimax,
+    HeuristicEasy,
+    HeuristicMedium,
+    HeuristicHard,
+    LinearQLearning,
+    Dqn,
     None
 }
 
-#[derive(Clone, Copy, Debug)]
+impl AgentType {
+    /// Search agents simulate games themselves, which makes them far too slow to be simulated in turn
+    pub fn is_search(self) -> bool {
+        self == AgentType::RandomRollout || self == AgentType::Dijkstra || self == AgentType::Mcts ||
+        self == AgentType::Minimax
+    }
+
+    /// Q-learning agents get the rewards of their actions in `GameEngine::rewards`
+    pub fn is_q_learning(self) -> bool {
+        self == AgentType::TabularQLearning || self == AgentType::LinearQLearning || self == AgentType::Dqn
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Intent {
     None,
     Move(Vector2),
     Dash(Vector2),
     Throw(::frisbee::ThrowDirection),
+    Lob(::frisbee::ThrowDirection),
+    Curve(::frisbee::CurveDirection),
 }
 
 fn simulation(engine: &mut GameEngine, side: &PlayerSide, intent: Intent, nb_frames : f64) -> (i8, Intent) {
@@ -47,6 +68,52 @@ fn simulation(engine: &mut GameEngine, side: &PlayerSide, intent: Intent, nb_fra
     (score, intent)
 }
 
+/// Every way of throwing the frisbee tried by the search agents
+pub fn throw_intents() -> Vec<Intent> {
+    let mut intents = Vec::with_capacity(8);
+    for dir in &[ThrowDirection::Up, ThrowDirection::LightUp, ThrowDirection::Middle, ThrowDirection::LightDown, ThrowDirection::Down] {
+        intents.push(Intent::Throw(*dir));
+    }
+    intents.push(Intent::Lob(ThrowDirection::Middle));
+    intents.push(Intent::Curve(CurveDirection::Up));
+    intents.push(Intent::Curve(CurveDirection::Down));
+    intents
+}
+
+/// The eight directions a player can move or dash in
+pub fn move_directions() -> [Vector2; 8] {
+    [
+        Vector2::new(0.0, 1.0),
+        Vector2::new(0.0, -1.0),
+        Vector2::new(-1.0, 0.0),
+        Vector2::new(1.0, 0.0),
+        Vector2::new(-1.0, -1.0).normalized(),
+        Vector2::new(-1.0, 1.0).normalized(),
+        Vector2::new(1.0, -1.0).normalized(),
+        Vector2::new(1.0, 1.0).normalized(),
+    ]
+}
+
+/// Moves back in front of the goal, in line with the frisbee, to wait for the next throw
+pub fn return_home(engine: &GameEngine, side: PlayerSide) -> Intent {
+    let config = &engine.config;
+    let player = match side {
+        PlayerSide::Left => &engine.players.0,
+        PlayerSide::Right => &engine.players.1,
+    };
+    let bound = config.wall_vertical - config.player_radius;
+    let home = Vector2::new(
+        player.get_horizontal_position() * config.player_start_x,
+        engine.frisbee.pos.y.max(-bound).min(bound),
+    );
+    let to_home = home - player.pos;
+    if to_home.length() <= config.player_move_speed {
+        Intent::None
+    } else {
+        Intent::Move(to_home.normalized())
+    }
+}
+
 pub fn agent_type_from_i8(side: i8) -> AgentType {
     match side {
         0 => AgentType::HumanPlayer,
@@ -54,16 +121,87 @@ pub fn agent_type_from_i8(side: i8) -> AgentType {
         2 => AgentType::RandomRollout,
         3 => AgentType::Dijkstra,
         4 => AgentType::TabularQLearning,
+        5 => AgentType::Mcts,
+        6 => AgentType::Minimax,
+        7 => AgentType::HeuristicEasy,
+        8 => AgentType::HeuristicMedium,
+        9 => AgentType::HeuristicHard,
+        10 => AgentType::LinearQLearning,
+        11 => AgentType::Dqn,
         _ => AgentType::None
     }
 }
 
-pub trait Agent {
+/// Agent type from its name on the command line
+pub fn agent_type_from_name(name: &str) -> Option<AgentType> {
+    match name {
+        "random" => Some(AgentType::Random),
+        "rollout" => Some(AgentType::RandomRollout),
+        "dijkstra" => Some(AgentType::Dijkstra),
+        "qlearning" => Some(AgentType::TabularQLearning),
+        "linear" => Some(AgentType::LinearQLearning),
+        "dqn" => Some(AgentType::Dqn),
+        "mcts" => Some(AgentType::Mcts),
+        "minimax" => Some(AgentType::Minimax),
+        "easy" => Some(AgentType::HeuristicEasy),
+        "medium" => Some(AgentType::HeuristicMedium),
+        "hard" => Some(AgentType::HeuristicHard),
+        _ => None,
+    }
+}
+
+/// Agent from a command line description `name[:frames[:sim[:time budget]]]`
+pub fn parse_agent(spec: &str) -> Option<AgentConfig> {
+    let mut parts = spec.split(':');
+    let agent_type = agent_type_from_name(parts.next()?)?;
+    let frames = match parts.next() {
+        Some(frames) => frames.parse().ok()?,
+        None => 60.0,
+    };
+    let sim = match parts.next() {
+        Some(sim) => sim.parse().ok()?,
+        None => 10,
+    };
+    let time_budget = match parts.next() {
+        Some(time_budget) => time_budget.parse().ok()?,
+        None => 0.0,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(AgentConfig { frames, sim, time_budget, ..AgentConfig::new(agent_type) })
+}
+
+/// Everything needed to create an agent again, see `GameEngine::create_agent`.
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct AgentConfig {
+    pub agent_type:  AgentType,
+    pub frames:      f64,
+    pub sim:         i8,
+    pub time_budget: f64, // Search time in seconds, only used by `MctsAgent` and `MinimaxAgent`
+}
+
+impl AgentConfig {
+    pub fn new(agent_type: AgentType) -> Self {
+        Self {
+            agent_type,
+            frames:      0.0,
+            sim:         0,
+            time_budget: 0.0,
+        }
+    }
+}
+
+/// Agents are `Send` so that training engines can run on worker threads.
+pub trait Agent: Send {
     fn act(&mut self, side: PlayerSide, engine: &mut GameEngine) -> Intent;
     fn get_type(&self) -> AgentType;
 
-    fn get_random_direction(&self) -> Vector2 {
-        let mut rng = ::rand::thread_rng();
+    fn get_config(&self) -> AgentConfig {
+        AgentConfig::new(self.get_type())
+    }
+
+    fn get_random_direction(&self, rng: &mut EngineRng) -> Vector2 {
         let dir = Vector2::new(
             rng.gen_range(-1.0, 1.0),
             rng.gen_range(-1.0, 1.0)
@@ -79,15 +217,21 @@ impl Agent for RandomAgent {
         AgentType::Random
     }
     fn act(&mut self, side: PlayerSide, engine: &mut GameEngine) -> Intent {
-        let mut rng = ::rand::thread_rng();
+        let rng = &mut engine.agent_rng;
 
         match engine.frisbee.held_by_player {
             Some(held_side) if held_side == side => {
                 // The agent holds the frisbee
                 let rand = rng.gen_range(0.0, 1.0);
-                if rand < 0.25 {
+                if rand < 0.15 {
                     // Throw
-                    return Intent::Throw(::frisbee::random_throw_direction());
+                    return Intent::Throw(::frisbee::random_throw_direction(rng));
+                } else if rand < 0.2 {
+                    // Curve
+                    return Intent::Curve(::frisbee::random_curve_direction(rng));
+                } else if rand < 0.25 {
+                    // Lob
+                    return Intent::Lob(::frisbee::random_throw_direction(rng));
                 } else {
                     // Wait, throw later
                 }
@@ -97,11 +241,11 @@ impl Agent for RandomAgent {
                 let rand = rng.gen_range(0.0, 1.0);
                 if rand < 0.5 {
                     // Move
-                    let dir = self.get_random_direction();
+                    let dir = self.get_random_direction(rng);
                     return Intent::Move(dir);
                 } else if rand < 0.6 {
                     // Dash
-                    let dir = self.get_random_direction();
+                    let dir = self.get_random_direction(rng);
                     return Intent::Dash(dir);
                 } else {
                     // Wait
@@ -116,6 +260,7 @@ impl Agent for RandomAgent {
 pub struct HumanPlayerAgent {}
 
 bitflags! {
+    #[repr(transparent)]
     pub struct HumanIntent: u8 {
         const IDLE  = 0;
         const UP    = 1;
@@ -123,6 +268,7 @@ bitflags! {
         const LEFT  = 4;
         const RIGHT = 8;
         const THROW = 16;
+        const LOB   = 32;
     }
 }
 
@@ -166,11 +312,38 @@ pub fn human_intent_from_index(idx: u8) -> HumanIntent {
     HumanIntent::IDLE
 }
 
-pub fn human_intent_to_intent(engine: &GameEngine, input: HumanIntent, side: PlayerSide) -> Intent {
-    let has_frisbee = match engine.frisbee.held_by_player {
-        Some(held_by) if held_by == side => true,
-        _ => false,
+/// Swaps left and right, to go between the court and the court mirrored for the right player
+fn mirror_human_intent(intent: HumanIntent) -> HumanIntent {
+    let mut mirrored = intent - (HumanIntent::LEFT | HumanIntent::RIGHT);
+    if intent.contains(HumanIntent::LEFT) {
+        mirrored |= HumanIntent::RIGHT;
+    }
+    if intent.contains(HumanIntent::RIGHT) {
+        mirrored |= HumanIntent::LEFT;
+    }
+    mirrored
+}
+
+/// Index in the Q-values of `intent` sent by `side`, on the court mirrored for the right player
+pub fn action_index(intent: HumanIntent, side: PlayerSide) -> usize {
+    let intent = match side {
+        PlayerSide::Left => intent,
+        PlayerSide::Right => mirror_human_intent(intent),
     };
+    human_intent_to_index(intent) as usize
+}
+
+/// Input sent by `side` for the action at `index`, see `action_index`
+pub fn action_from_index(index: usize, side: PlayerSide) -> HumanIntent {
+    let intent = human_intent_from_index(index as u8);
+    match side {
+        PlayerSide::Left => intent,
+        PlayerSide::Right => mirror_human_intent(intent),
+    }
+}
+
+pub fn human_intent_to_intent(engine: &GameEngine, input: HumanIntent, side: PlayerSide) -> Intent {
+    let has_frisbee = engine.frisbee.held_by_player == Some(side);
 
     let mut dir = Vector2::zero();
     if input.contains(HumanIntent::UP) {
@@ -187,25 +360,42 @@ pub fn human_intent_to_intent(engine: &GameEngine, input: HumanIntent, side: Pla
     }
     dir.normalize();
 
-    if input.contains(HumanIntent::THROW) {
+    if input.intersects(HumanIntent::THROW | HumanIntent::LOB) {
         if has_frisbee {
+            let (forward, backward) = match side {
+                PlayerSide::Left => (input.contains(HumanIntent::RIGHT), input.contains(HumanIntent::LEFT)),
+                PlayerSide::Right => (input.contains(HumanIntent::LEFT), input.contains(HumanIntent::RIGHT)),
+            };
+
+            // Holding up or down while pulling back curves the shot towards that side
+            if backward && !input.contains(HumanIntent::LOB) {
+                if input.contains(HumanIntent::UP) {
+                    return Intent::Curve(CurveDirection::Up);
+                }
+                if input.contains(HumanIntent::DOWN) {
+                    return Intent::Curve(CurveDirection::Down);
+                }
+            }
+
             let mut throw_dir = ThrowDirection::Middle;
             if input.contains(HumanIntent::UP) {
-                if (input.contains(HumanIntent::RIGHT) && side == PlayerSide::Left) ||
-                    (input.contains(HumanIntent::LEFT) && side == PlayerSide::Right) {
+                if forward {
                     throw_dir = ThrowDirection::LightUp;
                 } else {
                     throw_dir = ThrowDirection::Up;
                 }
             } else if input.contains(HumanIntent::DOWN) {
-                if (input.contains(HumanIntent::RIGHT) && side == PlayerSide::Left) ||
-                    (input.contains(HumanIntent::LEFT) && side == PlayerSide::Right) {
+                if forward {
                     throw_dir = ThrowDirection::LightDown;
                 } else {
                     throw_dir = ThrowDirection::Down;
                 }
             }
-            Intent::Throw(throw_dir)
+            if input.contains(HumanIntent::LOB) {
+                Intent::Lob(throw_dir)
+            } else {
+                Intent::Throw(throw_dir)
+            }
         } else {
             Intent::Dash(dir)
         }
@@ -237,17 +427,26 @@ impl Agent for RandomRolloutAgent {
     fn get_type(&self) -> AgentType {
         AgentType::RandomRollout
     }
+    fn get_config(&self) -> AgentConfig {
+        AgentConfig {
+            frames: self.frames,
+            sim: self.sim,
+            ..AgentConfig::new(AgentType::RandomRollout)
+        }
+    }
     fn act(&mut self, side: PlayerSide, engine: &mut GameEngine) -> Intent {
         let mut prev = (0, Intent::None);
         let mut new_engine = GameEngine::new();
-        let player = match side {
-            PlayerSide::Left => &engine.players.0,
-            PlayerSide::Right => &engine.players.1,
+        let is_sliding = match side {
+            PlayerSide::Left => engine.players.0.slide.is_some(),
+            PlayerSide::Right => engine.players.1.slide.is_some(),
         };
 
-        fn run_simulation(prev: &mut (i8, Intent), engine: &GameEngine, new_game_engine: &mut GameEngine, side: &PlayerSide, intent: Intent,frames: f64) {
+        fn run_simulation(prev: &mut (i8, Intent), engine: &mut GameEngine, new_game_engine: &mut GameEngine, side: &PlayerSide, intent: Intent, frames: f64) {
             engine.copy_in(new_game_engine);
-            let test = simulation(new_game_engine, side, intent,frames);
+            // Each rollout gets its own random stream so that repeated simulations explore different games
+            new_game_engine.agent_rng = engine.fork_agent_rng();
+            let test = simulation(new_game_engine, side, intent, frames);
             if prev.0 < test.0 {
                 prev.0 = test.0;
                 prev.1 = test.1;
@@ -259,36 +458,39 @@ impl Agent for RandomRolloutAgent {
             match engine.frisbee.held_by_player {
                 Some(held_by) if held_by == side => {
                     // If the agent holds the frisbee
-                    run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::Up),self.frames);
-                    run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::LightUp),self.frames);
-                    run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::Middle),self.frames);
-                    run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::LightDown),self.frames);
-                    run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::Down),self.frames);
+                    run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::Up), self.frames);
+                    run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::LightUp), self.frames);
+                    run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::Middle), self.frames);
+                    run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::LightDown), self.frames);
+                    run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::Down), self.frames);
+                    run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Lob(::frisbee::ThrowDirection::Middle), self.frames);
+                    run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Curve(CurveDirection::Up), self.frames);
+                    run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Curve(CurveDirection::Down), self.frames);
                 },
                 _ => {
                     // If the agent doesn't hold the frisbee
-                    if player.slide.is_none() {
+                    if !is_sliding {
                         // Movements are allowed only if the player is not dashing,
                         // so we're saving computing time if they are dashing
 
                         // TODO: use `human_intent_to_intent()` to replace the `Vector2::new`s with combined UP / DOWN / LEFT / RIGHT.
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Move(Vector2::new(0.0, 1.0)),self.frames);
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Move(Vector2::new(0.0, -1.0)),self.frames);
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Move(Vector2::new(-1.0, 0.0)),self.frames);
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Move(Vector2::new(1.0, 0.0)),self.frames);
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Move(Vector2::new(-1.0, -1.0).normalized()),self.frames);
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Move(Vector2::new(-1.0, 1.0).normalized()),self.frames);
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Move(Vector2::new(1.0, -1.0).normalized()),self.frames);
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Move(Vector2::new(1.0, 1.0).normalized()),self.frames);
-
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Dash(Vector2::new(0.0, 1.0)),self.frames);
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Dash(Vector2::new(0.0, -1.0)),self.frames);
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Dash(Vector2::new(-1.0, 0.0)),self.frames);
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Dash(Vector2::new(1.0, 0.0)),self.frames);
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Dash(Vector2::new(-1.0, -1.0).normalized()),self.frames);
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Dash(Vector2::new(-1.0, 1.0).normalized()),self.frames);
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Dash(Vector2::new(1.0, -1.0).normalized()),self.frames);
-                        run_simulation(&mut prev, &engine, &mut new_engine, &side, Intent::Dash(Vector2::new(1.0, 1.0).normalized()),self.frames);
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Move(Vector2::new(0.0, 1.0)), self.frames);
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Move(Vector2::new(0.0, -1.0)), self.frames);
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Move(Vector2::new(-1.0, 0.0)), self.frames);
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Move(Vector2::new(1.0, 0.0)), self.frames);
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Move(Vector2::new(-1.0, -1.0).normalized()), self.frames);
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Move(Vector2::new(-1.0, 1.0).normalized()), self.frames);
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Move(Vector2::new(1.0, -1.0).normalized()), self.frames);
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Move(Vector2::new(1.0, 1.0).normalized()), self.frames);
+
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Dash(Vector2::new(0.0, 1.0)), self.frames);
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Dash(Vector2::new(0.0, -1.0)), self.frames);
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Dash(Vector2::new(-1.0, 0.0)), self.frames);
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Dash(Vector2::new(1.0, 0.0)), self.frames);
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Dash(Vector2::new(-1.0, -1.0).normalized()), self.frames);
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Dash(Vector2::new(-1.0, 1.0).normalized()), self.frames);
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Dash(Vector2::new(1.0, -1.0).normalized()), self.frames);
+                        run_simulation(&mut prev, engine, &mut new_engine, &side, Intent::Dash(Vector2::new(1.0, 1.0).normalized()), self.frames);
                     }
                 }
             };
@@ -298,208 +500,22 @@ impl Agent for RandomRolloutAgent {
     }
 }
 
-pub struct DijkstraAgent {}
-
-pub struct Node {
-    pub engine: GameEngine,
-    pub first_intent: Intent,
-    pub cost: i64,
-    pub score: i64
-}
-
-
-pub fn get_best(nodes: &Vec<Node>) -> Vec<Node> {
-        let mut max_score = 0;
-        let mut max_nodes: Vec<Node> = Vec::new();
-
-        for i in nodes.iter() {
-            if i.score > max_score {
-                max_score = i.score;
-            } 
-        }
-
-        for i in nodes.iter() {
-            if i.score == max_score {
-                let mut game_engine = GameEngine::new();
-                i.engine.copy_in(&mut game_engine);
-                max_nodes.push(Node { engine: game_engine, first_intent: i.first_intent, cost: i.cost, score: i.score });
-            } 
-        }
-
-        max_nodes
-    }
-
-fn simulation_dij(engine: &mut GameEngine, side: &PlayerSide, intent: Intent, nodes: &mut Vec<Node>, score:  i64, cost: i64) {
-    
-
-    if cost >= 1000000000000 || engine.state_of_game != StateOfGame::Playing {return;}
-    let intents = match *side {
-        PlayerSide::Left => (intent, Intent::None),
-        PlayerSide::Right => (Intent::None, intent),
-    };
-    let mut add_score = 0;
-    let distance_before = match *side {
-        PlayerSide::Left => (engine.frisbee.pos - engine.players.0.pos).length(),
-        PlayerSide::Right => (engine.frisbee.pos - engine.players.1.pos).length(),
-    };
-    engine.step(intents);
-    let distance_after = match *side {
-        PlayerSide::Left => (engine.frisbee.pos - engine.players.0.pos).length(),
-        PlayerSide::Right => (engine.frisbee.pos - engine.players.1.pos).length(),
-    };
-
-    if distance_after < distance_before {
-        add_score += 1000;
-    }
-    if distance_after > distance_before {
-        add_score -= 100;
-    }
-    if distance_after == distance_before {
-        add_score -= 50;
-    }
+pub struct TabularQLearningAgent {}
+pub const QVALUES_ACTIONS: usize = 17;
 
-    let player = match side {
-            PlayerSide::Left => &engine.players.0,
-            PlayerSide::Right => &engine.players.1,
-    };
+/// Action with the highest Q-value, the first one in case of a tie
+pub fn greedy_action(q_values: &[f32; QVALUES_ACTIONS]) -> usize {
+    let mut idx = 0;
 
-    match engine.frisbee.held_by_player {
-        Some(held_by) if held_by == *side =>  add_score = 100000,
-        _ =>{}
-    }; 
-    let mut new_engine = GameEngine::new();
-
-    let mut node_engine = GameEngine::new();
-    engine.copy_in(&mut node_engine);
-    let node = Node { engine: node_engine, first_intent: intent, cost: cost, score: add_score + score as i64 };
-    nodes.push(node);
- 
-
-    match engine.frisbee.held_by_player {
-        Some(held_by) if held_by == *side => {
-            // If the agent holds the frisbee
-            simulation_dij(&mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::Up), nodes, add_score + score+ 3000 +(player.score) as i64, cost+1);
-            simulation_dij(&mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::LightUp), nodes, add_score + score+ 4000 +(player.score) as i64, cost+1);
-            simulation_dij(&mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::Middle), nodes, add_score + score+ 2000 +(player.score) as i64, cost+1);
-            simulation_dij(&mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::LightDown), nodes, add_score + score+ 4000 +(player.score) as i64, cost+1);
-            simulation_dij(&mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::Down), nodes, add_score + score+ 3000+(player.score) as i64, cost+1);
-        },
-        _ => {
-            // If the agent doesn't hold the frisbee
-            if player.slide.is_none() {
-                // Movements are allowed only if the player is not dashing,
-                // so we're saving computing time if they are dashing
-
-                simulation_dij(&mut new_engine, &side, Intent::Move(Vector2::new(0.0, 1.0)), nodes,add_score + score +(player.score + 1) as i64, cost+1);
-                simulation_dij(&mut new_engine, &side, Intent::Move(Vector2::new(0.0, -1.0)), nodes,add_score + score +(player.score + 1) as i64, cost+1);
-                simulation_dij(&mut new_engine, &side, Intent::Move(Vector2::new(-1.0, 0.0)), nodes,add_score + score +(player.score + 1) as i64, cost+1);
-                simulation_dij(&mut new_engine, &side, Intent::Move(Vector2::new(1.0, 0.0)),  nodes,add_score + score +(player.score + 1) as i64, cost+1);
-                simulation_dij(&mut new_engine, &side, Intent::Move(Vector2::new(-1.0, -1.0).normalized()), nodes,add_score + score +(player.score + 1) as i64, cost+1);
-                simulation_dij(&mut new_engine, &side, Intent::Move(Vector2::new(-1.0, 1.0).normalized()), nodes,add_score + score +(player.score + 1) as i64, cost+1);
-                simulation_dij(&mut new_engine, &side, Intent::Move(Vector2::new(1.0, -1.0).normalized()), nodes,add_score + score +(player.score + 1) as i64, cost+1);
-                simulation_dij(&mut new_engine, &side, Intent::Move(Vector2::new(1.0, 1.0).normalized()), nodes,add_score + score +(player.score + 1) as i64, cost+1);
-
-                simulation_dij(&mut new_engine, &side, Intent::Dash(Vector2::new(0.0, 1.0)), nodes, add_score + score +(player.score + 1) as i64, cost+4);
-                simulation_dij(&mut new_engine, &side, Intent::Dash(Vector2::new(0.0, -1.0)), nodes, add_score + score +(player.score + 1) as i64, cost+4);
-                simulation_dij(&mut new_engine, &side, Intent::Dash(Vector2::new(-1.0, 0.0)), nodes, add_score + score +(player.score + 1) as i64, cost+4);
-                simulation_dij(&mut new_engine, &side, Intent::Dash(Vector2::new(1.0, 0.0)), nodes, add_score + score +(player.score + 1) as i64, cost+4);
-                simulation_dij(&mut new_engine, &side, Intent::Dash(Vector2::new(-1.0, -1.0).normalized()), nodes, add_score + score +(player.score + 1) as i64, cost+4);
-                simulation_dij(&mut new_engine, &side, Intent::Dash(Vector2::new(-1.0, 1.0).normalized()), nodes, add_score + score +(player.score + 1) as i64, cost+4);
-                simulation_dij(&mut new_engine, &side, Intent::Dash(Vector2::new(1.0, -1.0).normalized()), nodes, add_score + score +(player.score + 1) as i64, cost+4);
-                simulation_dij(&mut new_engine, &side, Intent::Dash(Vector2::new(1.0, 1.0).normalized()), nodes, add_score + score +(player.score + 1) as i64, cost+4);
-            }
+    for (key, &value) in q_values.iter().enumerate() {
+        if value > q_values[idx] {
+            idx = key;
         }
-    };
-}
-
-impl Agent for DijkstraAgent {
-    fn get_type(&self) -> AgentType {
-        AgentType::Dijkstra
     }
-    fn act(&mut self, side: PlayerSide, engine: &mut GameEngine) -> Intent {
-        let mut new_engine = GameEngine::new();
-        let player = match side {
-            PlayerSide::Left => &engine.players.0,
-            PlayerSide::Right => &engine.players.1,
-        };
-
-
-        let mut nodes: Vec<Node> = Vec::new();
-        let mut node_engine = GameEngine::new();
-        engine.copy_in(&mut node_engine);
-        let node = Node { engine: node_engine, first_intent: Intent::None, cost: -1, score: player.score as i64 };
-        nodes.push(node);
-
-        fn run_simulation(engine: &GameEngine, new_game_engine: &mut GameEngine, side: &PlayerSide, intent: Intent, nodes: &mut Vec<Node>, score: i64) {
-            engine.copy_in(new_game_engine);
-            let mut node_engine = GameEngine::new();
-            engine.copy_in(&mut node_engine);
-            let node = Node { engine: node_engine, first_intent: intent, cost: -1, score: score as i64 };
-            nodes.push(node);
-            simulation_dij(new_game_engine, side, intent, nodes, score, 0);
-        }
-
-
-        match engine.frisbee.held_by_player {
-            Some(held_by) if held_by == side => {
-                // If the agent holds the frisbee
-                run_simulation(&engine, &mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::Up), &mut nodes, (player.score + 30) as i64);
-                run_simulation(&engine, &mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::LightUp), &mut nodes, (player.score + 40) as i64);
-                run_simulation(&engine, &mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::Middle), &mut nodes, (player.score + 20) as i64);
-                run_simulation(&engine, &mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::LightDown), &mut nodes, (player.score + 40) as i64);
-                run_simulation(&engine, &mut new_engine, &side, Intent::Throw(::frisbee::ThrowDirection::Down), &mut nodes, (player.score + 30) as i64);
-            },
-            _ => {
-                // If the agent doesn't hold the frisbee
-                if player.slide.is_none() {
-                    // Movements are allowed only if the player is not dashing,
-                    // so we're saving computing time if they are dashing
-
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Move(Vector2::new(0.0, 1.0)), &mut nodes,(player.score + 1) as i64);
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Move(Vector2::new(0.0, -1.0)), &mut nodes, (player.score + 1) as i64);
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Move(Vector2::new(-1.0, 0.0)), &mut nodes, (player.score + 1) as i64);
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Move(Vector2::new(1.0, 0.0)), &mut nodes, (player.score + 1) as i64);
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Move(Vector2::new(-1.0, -1.0).normalized()), &mut nodes,(player.score + 1) as i64);
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Move(Vector2::new(-1.0, 1.0).normalized()), &mut nodes,(player.score + 1) as i64);
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Move(Vector2::new(1.0, -1.0).normalized()), &mut nodes,(player.score + 1) as i64);
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Move(Vector2::new(1.0, 1.0).normalized()), &mut nodes,(player.score + 1) as i64);
-
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Dash(Vector2::new(0.0, 1.0)), &mut nodes, (player.score + 1) as i64);
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Dash(Vector2::new(0.0, -1.0)), &mut nodes, (player.score + 1) as i64);
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Dash(Vector2::new(-1.0, 0.0)), &mut nodes, (player.score + 1) as i64);
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Dash(Vector2::new(1.0, 0.0)), &mut nodes, (player.score + 1) as i64);
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Dash(Vector2::new(-1.0, -1.0).normalized()), &mut nodes, (player.score + 1) as i64);
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Dash(Vector2::new(-1.0, 1.0).normalized()), &mut nodes, (player.score + 1) as i64);
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Dash(Vector2::new(1.0, -1.0).normalized()), &mut nodes, (player.score + 1) as i64);
-                    run_simulation(&engine, &mut new_engine, &side, Intent::Dash(Vector2::new(1.0, 1.0).normalized()), &mut nodes, (player.score + 1) as i64);
-                }
-            }
-        };
-
-        let best : Vec<Node> = get_best(&nodes);
-        let mut cost = best[0].cost;
-        let mut intent = best[0].first_intent;
-        let mut rng = ::rand::thread_rng();
-        for i in best.iter() {
-            println!("Getting best intent");
-            println!("intent : {:?}", i.first_intent);
-            if i.cost < cost {
-                cost = i.cost;
-                intent = i.first_intent;
-            }
-            if i.cost == cost && rng.gen_range(1, 100) > 50 {
-                cost = i.cost;
-                intent = i.first_intent;
-            }
-        }
 
-        intent
-    }
+    idx
 }
 
-pub struct TabularQLearningAgent {}
-pub const QVALUES_ACTIONS: usize = 17;
-pub type QValues = HashMap<u64, ([f32; QVALUES_ACTIONS], [f32; QVALUES_ACTIONS])>;
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ActionResult {
     None,
@@ -507,6 +523,7 @@ pub enum ActionResult {
     Dashed,
     GrabbedFrisbee,
     Threw,
+    PowerThrew,
 }
 
 impl Agent for TabularQLearningAgent {
@@ -514,46 +531,19 @@ impl Agent for TabularQLearningAgent {
         AgentType::TabularQLearning
     }
     fn act(&mut self, side: PlayerSide, engine: &mut GameEngine) -> Intent {
-        let mut rng = ::rand::thread_rng();
-        let intent: HumanIntent;
-
-        fn max_index(array: &[f32; QVALUES_ACTIONS]) -> usize {
-            let mut idx = 0;
-
-            for (key, &value) in array.iter().enumerate() {
-                if value > array[idx] {
-                    idx = key;
-                }
-            }
-
-            idx
-        }
-
-        if rng.gen_range(0.0, 1.0) < engine.explo_rate {
+        let intent_index = if engine.agent_rng.gen_range(0.0, 1.0) < engine.explo_rate {
             // Explore
-            let intent_index = rng.gen_range(0, QVALUES_ACTIONS);
-            intent = human_intent_from_index(intent_index as u8);
+            engine.agent_rng.gen_range(0, QVALUES_ACTIONS)
         } else {
-            // Exploit
-            let hash = engine.hash();
-            let intent_index = match side {
-                PlayerSide::Left => {
-                    if engine.q_values.contains_key(&hash) {
-                        max_index(&engine.q_values[&hash].0)
-                    } else {
-                        0
-                    }
-                },
-                PlayerSide::Right => {
-                    if engine.q_values.contains_key(&hash) {
-                        max_index(&engine.q_values[&hash].1)
-                    } else {
-                        0
-                    }
-                },
-            };
-            intent = human_intent_from_index(intent_index as u8);
-        }
+            // Exploit, each side with its half of the table on its own mirrored court
+            let state = engine.q_state(side);
+            match engine.q_values.get(state) {
+                Some(q_values) if side == PlayerSide::Left || engine.q_mirror => greedy_action(&q_values.0),
+                Some(q_values) => greedy_action(&q_values.1),
+                None => 0,
+            }
+        };
+        let intent = action_from_index(intent_index, side);
 
         match side {
             PlayerSide::Left => {
@@ -568,13 +558,3 @@ impl Agent for TabularQLearningAgent {
     }
 }
 
-pub fn get_blank_q_values() -> QValues {
-    let size: u64 = 206909; // This is the `max_value` from GameEngine::hash()
-    let mut map = QValues::with_capacity(size as usize);
-
-    for i in 0..size {
-        map.insert(i, ([0.0; QVALUES_ACTIONS], [0.0; QVALUES_ACTIONS]));
-    }
-
-    map
-}
@@ -0,0 +1,71 @@
+extern crate rustjammers_engine;
+
+use rustjammers_engine::agent::{ HumanIntent, parse_agent };
+use rustjammers_engine::config::GameConfig;
+use rustjammers_engine::game_engine::{ GameEngine, StateOfGame };
+
+fn usage() -> ! {
+    eprintln!("Usage: benchmark <agent 1> <agent 2> [matches] [config.json]");
+    eprintln!("Agents are given as name[:frames[:sim[:time budget]]], for example rollout:60:10 or minimax:0:4:0.005");
+    eprintln!("Agent names: random, rollout, dijkstra, qlearning, linear, dqn, mcts, minimax, and easy, medium or hard for the scripted bot");
+    std::process::exit(1);
+}
+
+/// Plays matches between two agents without rendering and prints the results.
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 2 {
+        usage();
+    }
+    let agents = match (parse_agent(&args[0]), parse_agent(&args[1])) {
+        (Some(a1), Some(a2)) => (a1, a2),
+        _ => usage(),
+    };
+    let matches: u32 = match args.get(2) {
+        Some(arg) => arg.parse().unwrap_or_else(|_| usage()),
+        None => 10,
+    };
+    let config = match args.get(3) {
+        Some(path) => match GameConfig::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Could not load the config \"{}\": {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => GameConfig::default(),
+    };
+
+    let mut wins = (0, 0);
+    let mut draws = 0;
+    let mut sets = (0, 0);
+    let start_time = std::time::Instant::now();
+    for i in 0..matches {
+        let mut engine = GameEngine::with_seed(i as u64);
+        engine.config = config;
+        engine.send_type_p1(agents.0.agent_type as i8, agents.0.frames, agents.0.sim, agents.0.time_budget);
+        engine.send_type_p2(agents.1.agent_type as i8, agents.1.frames, agents.1.sim, agents.1.time_budget);
+        engine.reset();
+
+        while engine.state_of_game != StateOfGame::End {
+            engine.epoch(HumanIntent::IDLE, HumanIntent::IDLE);
+        }
+
+        let result = (engine.players.0.sets, engine.players.1.sets);
+        sets.0 += result.0 as u32;
+        sets.1 += result.1 as u32;
+        if result.0 > result.1 {
+            wins.0 += 1;
+        } else if result.1 > result.0 {
+            wins.1 += 1;
+        } else {
+            draws += 1;
+        }
+        println!("Match {}: {} - {}", i + 1, result.0, result.1);
+    }
+
+    println!();
+    println!("{} vs {}, {} matches in {}s", args[0], args[1], matches, start_time.elapsed().as_secs());
+    println!("Wins: {} - {}, draws: {}", wins.0, wins.1, draws);
+    println!("Sets: {} - {}", sets.0, sets.1);
+}
@@ -1,3 +1,4 @@
+use config::GameConfig;
 use frisbee::Frisbee;
 use vector2::Vector2;
 use player::{ Player, PlayerSide };
@@ -7,78 +8,199 @@ struct Circle {
     pub radius: f64
 }
 
-pub fn player_collision(player: &mut Player) -> bool {
+pub fn player_collision(player: &mut Player, config: &GameConfig) -> bool {
     let side = player.get_horizontal_position();
     let mut collided = false;
-    const WALL_EXT: f64 = 9.4;
-    const NET: f64 = 0.75;
-    const WALL_VERTICAL: f64 = 4.4;
+    let wall_ext = config.wall_ext;
+    let net = config.net;
+    let wall_vertical = config.wall_vertical;
 
     if side > 0.0 {
-        if player.pos.x > WALL_EXT {
-            player.pos.x = WALL_EXT;
+        if player.pos.x > wall_ext {
+            player.pos.x = wall_ext;
             collided = true;
         }
-        if player.pos.x < NET {
-            player.pos.x = NET;
+        if player.pos.x < net {
+            player.pos.x = net;
             collided = true;
         }
     }
     if side < 0.0 {
-        if player.pos.x < -WALL_EXT {
-            player.pos.x = -WALL_EXT;
+        if player.pos.x < -wall_ext {
+            player.pos.x = -wall_ext;
             collided = true;
         }
-        if player.pos.x > -NET {
-            player.pos.x = -NET;
+        if player.pos.x > -net {
+            player.pos.x = -net;
             collided = true;
         }
     }
-    if player.pos.y > WALL_VERTICAL {
-        player.pos.y = WALL_VERTICAL;
+    if player.pos.y > wall_vertical {
+        player.pos.y = wall_vertical;
         collided = true;
     }
-    if player.pos.y < -WALL_VERTICAL {
-        player.pos.y = -WALL_VERTICAL;
+    if player.pos.y < -wall_vertical {
+        player.pos.y = -wall_vertical;
         collided = true;
     }
 
     collided
 }
 
-pub fn frisbee_collision_wall(frisbee: &mut Frisbee) {
-    const WALL_VERTICAL: f64 = 4.4;
+/// Time of impact, between 0 and 1, of a point moving by `motion` from `start` with a circle.
+/// Returns 0 if the point already is inside the circle.
+pub fn sweep_circle(start: Vector2, motion: Vector2, center: Vector2, radius: f64) -> Option<f64> {
+    let offset = start - center;
+    let c = offset.dot(&offset) - radius * radius;
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+    let a = motion.dot(&motion);
+    let b = 2.0 * motion.dot(&offset);
+    if a == 0.0 || b >= 0.0 {
+        // Not moving, or moving away from the circle
+        return None;
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = (-b - discriminant.sqrt()) / (2.0 * a);
+    if t <= 1.0 {
+        Some(t)
+    } else {
+        None
+    }
+}
 
-    // TODO: check rebound angles
-    if frisbee.pos.y >= WALL_VERTICAL || frisbee.pos.y <= -WALL_VERTICAL {
-        frisbee.direction.y *= -1.0;
-        if frisbee.pos.y >= WALL_VERTICAL {
-            frisbee.direction = Vector2::new(frisbee.direction.x, -(frisbee.direction.y.abs())).normalized();
-        }
+/// Time of impact of the frisbee center with the top or bottom wall, along with the wall hit.
+/// The walls stop at the goal lines.
+pub fn sweep_walls(start: Vector2, motion: Vector2, config: &GameConfig) -> Option<(f64, Wall)> {
+    let (wall, y) = if motion.y > 0.0 {
+        (Wall::Top, config.wall_vertical)
+    } else if motion.y < 0.0 {
+        (Wall::Bottom, -config.wall_vertical)
+    } else {
+        return None;
+    };
 
-        if frisbee.pos.y <= -WALL_VERTICAL {
-            frisbee.direction = Vector2::new(frisbee.direction.x, frisbee.direction.y.abs()).normalized();
-        }
+    // Already past the wall when moving towards it, rebound right away
+    let t = ((y - start.y) / motion.y).max(0.0);
+    if t > 1.0 {
+        return None;
+    }
+    let x = start.x + motion.x * t;
+    if x.abs() > config.goal_line {
+        return None;
+    }
+    Some((t, wall))
+}
+
+/// Time of impact of the frisbee center with one of the goal lines.
+fn sweep_goal_lines(start: Vector2, motion: Vector2, config: &GameConfig) -> Option<f64> {
+    let x = if motion.x > 0.0 {
+        config.goal_line
+    } else if motion.x < 0.0 {
+        -config.goal_line
+    } else {
+        return None;
+    };
+    let t = ((x - start.x) / motion.x).max(0.0);
+    if t <= 1.0 {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wall {
+    Top,
+    Bottom,
+}
+
+enum Impact {
+    Player(PlayerSide),
+    Wall(Wall),
+    GoalLine,
+}
+
+/// Moves a thrown frisbee for one frame, bouncing off the walls at the contact point
+/// and stopping at the goal lines. Returns the side of the player who caught it on the way,
+/// if `can_catch` is set; see `player_collides_with_frisbee` for who can catch it.
+pub fn move_frisbee(frisbee: &mut Frisbee, players: &(Player, Player), config: &GameConfig, can_catch: bool) -> Option<PlayerSide> {
+    // Bounds the number of rebounds in a frame, in case the frisbee is stuck in a corner
+    const MAX_IMPACTS: usize = 8;
+
+    let mut remaining = 1.0;
+    for _ in 0..MAX_IMPACTS {
+        let motion = frisbee.direction * frisbee.speed * config.frisbee_speed_scale * remaining;
 
-        if frisbee.direction.y == 0.0 {
-            if frisbee.pos.y >= WALL_VERTICAL {
-                frisbee.pos.y = WALL_VERTICAL - 0.001;
-            } else if frisbee.pos.y <= -WALL_VERTICAL {
-                frisbee.pos.y = -WALL_VERTICAL + 0.001;
+        let mut impact: Option<(f64, Impact)> = None;
+        {
+            let mut consider = |t: Option<f64>, kind: Impact| {
+                if let Some(t) = t {
+                    match impact {
+                        Some((first, _)) if first <= t => {},
+                        _ => impact = Some((t, kind)),
+                    }
+                }
+            };
+
+            if can_catch {
+                for player in &[&players.0, &players.1] {
+                    if can_catch_frisbee(player, frisbee, config) {
+                        let t = sweep_circle(frisbee.pos, motion, player.pos, config.player_radius + config.frisbee_radius);
+                        consider(t, Impact::Player(player.side.unwrap()));
+                    }
+                }
+            }
+            if let Some((t, wall)) = sweep_walls(frisbee.pos, motion, config) {
+                consider(Some(t), Impact::Wall(wall));
             }
+            consider(sweep_goal_lines(frisbee.pos, motion, config), Impact::GoalLine);
+        }
+
+        match impact {
+            None => {
+                frisbee.pos += motion;
+                return None;
+            },
+            Some((t, kind)) => {
+                frisbee.pos += motion * t;
+                match kind {
+                    Impact::Player(side) => return Some(side),
+                    Impact::GoalLine => {
+                        // Exactly on the line, for `frisbee_collision_goal` to count the goal
+                        frisbee.pos.x = if motion.x > 0.0 { config.goal_line } else { -config.goal_line };
+                        return None;
+                    },
+                    Impact::Wall(wall) => {
+                        frisbee.pos.y = match wall {
+                            Wall::Top => config.wall_vertical,
+                            Wall::Bottom => -config.wall_vertical,
+                        };
+                        frisbee.direction.y = -frisbee.direction.y;
+                        // The rebound mirrors the path, so a curve bends the other way
+                        frisbee.spin = -frisbee.spin;
+                        remaining *= 1.0 - t;
+                    },
+                }
+            },
         }
     }
+    None
 }
 
-pub fn frisbee_collision_goal(frisbee: &mut Frisbee, players: &mut (Player, Player)) -> bool {
-    const WALL_EXT: f64 = 9.4 + 0.5;
-    const FIVE_POINTS_START: f64 = 3.3 / 2.0;
-    const FIVE_POINTS_END: f64 = -3.3 / 2.0;
+pub fn frisbee_collision_goal(frisbee: &mut Frisbee, players: &mut (Player, Player), config: &GameConfig) -> bool {
+    let goal_line = config.goal_line;
+    let five_points_start = config.five_points_band;
+    let five_points_end = -config.five_points_band;
 
-    if frisbee.pos.x >= WALL_EXT || frisbee.pos.x <= -WALL_EXT {
-        let mut points = 3;
-        if frisbee.pos.y > FIVE_POINTS_END && frisbee.pos.y < FIVE_POINTS_START {
-            points = 5;
+    if frisbee.pos.x >= goal_line || frisbee.pos.x <= -goal_line {
+        let mut points = config.goal_points;
+        if frisbee.pos.y > five_points_end && frisbee.pos.y < five_points_start {
+            points = config.five_points_goal_points;
         }
 
         match frisbee.last_held {
@@ -86,31 +208,62 @@ pub fn frisbee_collision_goal(frisbee: &mut Frisbee, players: &mut (Player, Play
             Some(PlayerSide::Right) => players.1.score += points,
             None => return false,
         }
-        frisbee.pos = Vector2::new(0.0, -4.0);
-        frisbee.speed = 0.0;
-        frisbee.direction = Vector2::zero();
+        frisbee.pos = Vector2::new(0.0, config.frisbee_start_y);
+        frisbee.stop();
         return true;
     }
     false
 }
 
-pub fn player_collides_with_frisbee(player: &Player, frisbee: &Frisbee) -> bool {
-    match frisbee.last_held {
-        Some(last_held) => {
-            if last_held == player.side.unwrap() {
-                return false;
-            }
-        },
-        None => {}
+/// Scores a lob that touches the ground without being caught.
+/// The thrower gets the points if it lands in the opponent's half, the opponent gets them otherwise.
+pub fn frisbee_collision_ground(frisbee: &mut Frisbee, players: &mut (Player, Player), config: &GameConfig) -> bool {
+    if !frisbee.is_lob() || frisbee.height > 0.0 {
+        return false;
+    }
+
+    let landed_in = if frisbee.pos.x > 0.0 { PlayerSide::Right } else { PlayerSide::Left };
+    let scorer = match frisbee.last_held {
+        Some(thrower) if thrower != landed_in => thrower,
+        Some(_) if landed_in == PlayerSide::Left => PlayerSide::Right,
+        Some(_) => PlayerSide::Left,
+        None => return false,
     };
+    match scorer {
+        PlayerSide::Left => players.0.score += config.drop_points,
+        PlayerSide::Right => players.1.score += config.drop_points,
+    }
+    // Like after a goal, the next throw goes to the player who conceded the points
+    frisbee.last_held = Some(scorer);
+    frisbee.pos = Vector2::new(0.0, config.frisbee_start_y);
+    frisbee.stop();
+    true
+}
+
+/// Whether the player is allowed to catch the frisbee: it must have been thrown by the other player,
+/// and fly low enough.
+fn can_catch_frisbee(player: &Player, frisbee: &Frisbee, config: &GameConfig) -> bool {
+    if let Some(last_held) = frisbee.last_held {
+        if last_held == player.side.unwrap() {
+            return false;
+        }
+    }
+    // Lobs fly over the players until they come back down
+    !frisbee.is_airborne(config)
+}
+
+pub fn player_collides_with_frisbee(player: &Player, frisbee: &Frisbee, config: &GameConfig) -> bool {
+    if !can_catch_frisbee(player, frisbee, config) {
+        return false;
+    }
 
     let player_bounds = Circle {
         center: player.pos,
-        radius: 0.5
+        radius: config.player_radius
     };
     let frisbee_bounds = Circle {
         center: frisbee.pos,
-        radius: 0.5
+        radius: config.frisbee_radius
     };
 
     let d2 = (player_bounds.center.x - frisbee_bounds.center.x).powf(2.0) + (player_bounds.center.y - frisbee_bounds.center.y).powf(2.0);
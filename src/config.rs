@@ -0,0 +1,180 @@
+use format::FileError;
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Court geometry, timing and rules of a match.
+///
+/// Distances are in court units, speeds are the distance travelled in one frame.
+/// Config files are JSON documents, missing fields keep their default value.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    // Court geometry
+    pub wall_ext:                f64, // Distance from the net to the back walls, for the players
+    pub net:                     f64, // Distance players have to keep from the net
+    pub wall_vertical:           f64, // Distance from the middle of the court to the top and bottom walls
+    pub goal_line:               f64, // Distance from the net to the goals, for the frisbee
+    pub five_points_band:        f64, // Half height of the five-point zone in the middle of the goals
+    pub player_radius:           f64,
+    pub frisbee_radius:          f64,
+    pub player_start_x:          f64,
+    pub frisbee_start_y:         f64,
+
+    // Timing
+    pub time_step:               f64, // Duration of a frame, in seconds
+    pub max_round_time:          f64, // Duration of a round
+    pub tie_break_time:          f64, // Duration of each round of the sudden death played when a set ends in a tie
+    pub kickoff_delay:           f64, // Time left to the players to get back in position after a goal
+    pub set_break_time:          f64, // Pause between two sets
+    pub max_hold_time:           f64, // The frisbee is thrown automatically when held longer than this
+
+    // Rules
+    pub best_of_sets:            i8, // The match is won by the first player to win the majority of these sets
+    pub rounds_per_set:          i8, // Timed rounds played in a set, its points adding up
+    pub max_round_points:        i8, // Points needed to win a set before the end of the timer
+    pub goal_points:             i8,
+    pub five_points_goal_points: i8,
+    pub drop_points:             i8, // Scored when a lob lands in the opponent's half
+    pub initial_frisbee_speed:   f64,
+    pub rally_speed_step:        f64, // Speed gained by the frisbee each time it is caught
+    pub max_frisbee_speed:       f64, // Cap of the throw speed, including power throws
+    pub power_throw_factor:      f64, // Speed multiplier of throws made while dashing or right after
+    pub power_throw_window:      f64, // Time after the end of a dash during which throws are power throws
+    pub frisbee_speed_scale:     f64, // Converts the frisbee speed into a distance per frame
+    pub player_move_speed:       f64,
+    pub player_dash_power:       f64, // Length of a dash
+    pub player_dash_speed:       f64,
+    pub curve_spin:              f64, // Angle a curve shot turns by each frame, in radians
+
+    // Lobs
+    pub lob_speed:               f64, // Horizontal speed of a lob, scaled like the frisbee speed
+    pub lob_vertical_speed:      f64, // Initial height gained per frame
+    pub gravity:                 f64, // Vertical speed lost per frame
+    pub catch_height:            f64, // A lob flying higher than this cannot be caught
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            wall_ext:                9.4,
+            net:                     0.75,
+            wall_vertical:           4.4,
+            goal_line:               9.9,
+            five_points_band:        3.3 / 2.0,
+            player_radius:           0.5,
+            frisbee_radius:          0.5,
+            player_start_x:          9.0,
+            frisbee_start_y:         -4.0,
+
+            time_step:               1.0 / 60.0,
+            max_round_time:          30.0,
+            tie_break_time:          30.0,
+            kickoff_delay:           1.0,
+            set_break_time:          3.0,
+            max_hold_time:           3.0,
+
+            best_of_sets:            3,
+            rounds_per_set:          2,
+            max_round_points:        30,
+            goal_points:             3,
+            five_points_goal_points: 5,
+            drop_points:             2,
+            initial_frisbee_speed:   2.5,
+            rally_speed_step:        0.15,
+            max_frisbee_speed:       6.0,
+            power_throw_factor:      1.5,
+            power_throw_window:      0.2,
+            frisbee_speed_scale:     0.1,
+            player_move_speed:       0.1,
+            player_dash_power:       2.5,
+            player_dash_speed:       0.4,
+            curve_spin:              0.012,
+
+            lob_speed:               2.0,
+            lob_vertical_speed:      0.12,
+            gravity:                 0.004,
+            catch_height:            1.0,
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.net < 0.0 || self.wall_ext <= self.net {
+            return Err(String::from("`wall_ext` must be greater than `net`, which must be positive"));
+        }
+        if self.wall_vertical <= 0.0 {
+            return Err(String::from("`wall_vertical` must be positive"));
+        }
+        if self.goal_line < self.wall_ext {
+            return Err(String::from("`goal_line` must be greater than or equal to `wall_ext`"));
+        }
+        if self.five_points_band < 0.0 || self.five_points_band > self.wall_vertical {
+            return Err(String::from("`five_points_band` must be between 0 and `wall_vertical`"));
+        }
+        if self.player_radius <= 0.0 || self.frisbee_radius <= 0.0 {
+            return Err(String::from("`player_radius` and `frisbee_radius` must be positive"));
+        }
+        if self.player_start_x < self.net || self.player_start_x > self.wall_ext {
+            return Err(String::from("`player_start_x` must be between `net` and `wall_ext`"));
+        }
+        if self.frisbee_start_y.abs() > self.wall_vertical {
+            return Err(String::from("`frisbee_start_y` must be between the walls"));
+        }
+        if self.time_step <= 0.0 || self.max_round_time <= 0.0 || self.tie_break_time <= 0.0 {
+            return Err(String::from("`time_step`, `max_round_time` and `tie_break_time` must be positive"));
+        }
+        if self.max_hold_time <= 0.0 {
+            return Err(String::from("`max_hold_time` must be positive"));
+        }
+        if self.kickoff_delay < 0.0 || self.set_break_time < 0.0 {
+            return Err(String::from("`kickoff_delay` and `set_break_time` cannot be negative"));
+        }
+        if self.best_of_sets <= 0 || self.best_of_sets % 2 == 0 {
+            return Err(String::from("`best_of_sets` must be a positive odd number"));
+        }
+        if self.rounds_per_set <= 0 {
+            return Err(String::from("`rounds_per_set` must be positive"));
+        }
+        if self.max_round_points <= 0 || self.goal_points <= 0 || self.five_points_goal_points <= 0 || self.drop_points <= 0 {
+            return Err(String::from("`max_round_points`, `goal_points`, `five_points_goal_points` and `drop_points` must be positive"));
+        }
+        if self.initial_frisbee_speed <= 0.0 || self.frisbee_speed_scale <= 0.0 ||
+           self.player_move_speed <= 0.0 || self.player_dash_power <= 0.0 || self.player_dash_speed <= 0.0 {
+            return Err(String::from("speeds and the dash power must be positive"));
+        }
+        if self.max_frisbee_speed < self.initial_frisbee_speed {
+            return Err(String::from("`max_frisbee_speed` must be greater than or equal to `initial_frisbee_speed`"));
+        }
+        if self.rally_speed_step < 0.0 || self.power_throw_factor < 1.0 || self.power_throw_window < 0.0 {
+            return Err(String::from("`rally_speed_step` and `power_throw_window` cannot be negative, `power_throw_factor` must be at least 1"));
+        }
+        if self.curve_spin < 0.0 {
+            return Err(String::from("`curve_spin` cannot be negative"));
+        }
+        if self.lob_speed <= 0.0 || self.lob_vertical_speed <= 0.0 || self.gravity <= 0.0 || self.catch_height < 0.0 {
+            return Err(String::from("`lob_speed`, `lob_vertical_speed` and `gravity` must be positive, `catch_height` cannot be negative"));
+        }
+        Ok(())
+    }
+
+    pub fn sets_to_win(&self) -> i8 {
+        self.best_of_sets / 2 + 1
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        let file = File::open(path)?;
+        let config: Self = ::serde_json::from_reader(BufReader::new(file)).map_err(|e| FileError::Invalid(e.to_string()))?;
+        config.validate().map_err(FileError::Invalid)?;
+        Ok(config)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), FileError> {
+        let file = File::create(path)?;
+        ::serde_json::to_writer_pretty(file, self).map_err(|e| FileError::Invalid(e.to_string()))?;
+        Ok(())
+    }
+}
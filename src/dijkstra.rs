@@ -0,0 +1,252 @@
+use agent::{ Agent, AgentType, Intent, throw_intents, move_directions, return_home };
+use game_engine::{ GameEngine, StateOfGame };
+use heuristic::predict_path;
+use player::{ Player, PlayerSide };
+use snapshot::{ Snapshot, AgentModel };
+
+use std::cmp::Reverse;
+use std::collections::{ BinaryHeap, HashSet };
+
+/// Nodes expanded each frame by default, which keeps a decision at about a millisecond
+pub const DEFAULT_MAX_NODES: usize = 256;
+/// Frames during which an action of the search is played
+const ACTION_FRAMES: u32 = 4;
+/// Extra cost of a dash, so that the player only dashes when walking is too slow
+const DASH_COST: u32 = 4;
+/// Paths longer than this many frames are not expanded
+const MAX_DEPTH: u32 = 180;
+/// Frames a throw is followed for before deciding that it does not score
+const THROW_HORIZON: u32 = 120;
+
+fn player(engine: &GameEngine, side: PlayerSide) -> &Player {
+    match side {
+        PlayerSide::Left => &engine.players.0,
+        PlayerSide::Right => &engine.players.1,
+    }
+}
+
+fn scores(engine: &GameEngine, side: PlayerSide) -> (i8, i8) {
+    match side {
+        PlayerSide::Left => (engine.players.0.score, engine.players.1.score),
+        PlayerSide::Right => (engine.players.1.score, engine.players.0.score),
+    }
+}
+
+/// Plays a frame in which the opponent stands still
+fn play_frame(engine: &mut GameEngine, side: PlayerSide, intent: Intent) {
+    match side {
+        PlayerSide::Left => engine.step((intent, Intent::None)),
+        PlayerSide::Right => engine.step((Intent::None, intent)),
+    }
+}
+
+/// Frames before the player can reach the frisbee along its predicted flight, see `predict_path`.
+/// Lower bound used as the heuristic of the search.
+fn intercept_frames(engine: &GameEngine, side: PlayerSide) -> u32 {
+    let config = &engine.config;
+    let player = player(engine, side);
+    let frisbee = &engine.frisbee;
+    let reach = config.player_radius + config.frisbee_radius;
+    let player_speed = config.player_move_speed.max(config.player_dash_speed);
+    let path = predict_path(frisbee, &engine.players, config);
+
+    let (mut pos, mut catchable) = (frisbee.pos, true);
+    for t in 0..MAX_DEPTH {
+        if t > 0 {
+            match path.get(t as usize - 1) {
+                Some(&(next, can_catch)) => {
+                    pos = next;
+                    catchable = can_catch;
+                },
+                // A still frisbee stays where it is, a thrown one reached a goal line or landed
+                None if frisbee.speed == 0.0 => {},
+                None => break,
+            }
+        }
+        let distance = (pos - player.pos).length() - reach;
+        if catchable && distance <= player_speed * t as f64 {
+            return t;
+        }
+    }
+    MAX_DEPTH
+}
+
+/// States that only differ by less than this are considered the same
+fn state_key(engine: &GameEngine, side: PlayerSide) -> (u64, i32, i32, bool) {
+    // `GameEngine::hash` only describes the left player, and too coarsely to tell moves apart
+    let player = player(engine, side);
+    (
+        engine.hash(),
+        (player.pos.x * 4.0).round() as i32,
+        (player.pos.y * 4.0).round() as i32,
+        player.slide.is_some(),
+    )
+}
+
+struct Node {
+    snapshot:     Snapshot,
+    first_intent: Intent,
+    cost:         u32, // Frames played since the root, plus the dash costs
+    frames:       u32,
+    caught:       bool,
+}
+
+/// A* search over the engine states towards catching an incoming frisbee,
+/// and a simulation of each throw when holding it.
+///
+/// The opponent is assumed to stand still. The search stops after `max_nodes` expansions,
+/// in which case the player heads for the node closest to the frisbee.
+pub struct DijkstraAgent {
+    pub max_nodes: usize,
+    sim_engine:    GameEngine,
+}
+
+impl DijkstraAgent {
+    pub fn new() -> Self {
+        Self {
+            max_nodes:  DEFAULT_MAX_NODES,
+            sim_engine: GameEngine::with_seed(0),
+        }
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) {
+        snapshot.restore_with(&mut self.sim_engine, (AgentModel::Rollout, AgentModel::Rollout));
+    }
+
+    /// Throw after which the player scores the most, as soon as possible
+    fn best_throw(&mut self, side: PlayerSide, engine: &GameEngine) -> Intent {
+        let root = Snapshot::take(engine);
+        let start_scores = scores(engine, side);
+        let mut best = (i32::MIN, 0, Intent::None);
+        for intent in throw_intents() {
+            self.restore(&root);
+            play_frame(&mut self.sim_engine, side, intent);
+            let mut frames = 1;
+            while frames < THROW_HORIZON && self.sim_engine.state_of_game == StateOfGame::Playing &&
+                  self.sim_engine.frisbee.held_by_player.is_none() {
+                play_frame(&mut self.sim_engine, side, Intent::None);
+                frames += 1;
+            }
+            let (own, opponent) = scores(&self.sim_engine, side);
+            let gain = (own - start_scores.0) as i32 - (opponent - start_scores.1) as i32;
+            if gain <= 0 {
+                // Caught, or still flying at the end of the horizon
+                frames = THROW_HORIZON;
+            }
+            if gain > best.0 || (gain == best.0 && frames < best.1) {
+                best = (gain, frames, intent);
+            }
+        }
+        best.2
+    }
+
+    /// First action of the cheapest path to the frisbee
+    fn search(&mut self, side: PlayerSide, engine: &GameEngine) -> Intent {
+        let mut nodes = vec![Node {
+            snapshot:     Snapshot::take(engine),
+            first_intent: Intent::None,
+            cost:         0,
+            frames:       0,
+            caught:       false,
+        }];
+        let mut heap = BinaryHeap::new();
+        let h = intercept_frames(engine, side);
+        heap.push(Reverse((h, h, 0)));
+        let mut visited = HashSet::new();
+        visited.insert(state_key(engine, side));
+        // Node closest to the frisbee, used when the search is cut short
+        let mut closest = (h, 0, 0);
+
+        let mut moves = Vec::with_capacity(16);
+        for dir in &move_directions() {
+            moves.push((Intent::Move(*dir), 0));
+        }
+        for dir in &move_directions() {
+            moves.push((Intent::Dash(*dir), DASH_COST));
+        }
+
+        let mut expanded = 0;
+        while let Some(Reverse((_, _, index))) = heap.pop() {
+            if nodes[index].caught {
+                // No cheaper path is left in the queue
+                return nodes[index].first_intent;
+            }
+            if expanded >= self.max_nodes {
+                break;
+            }
+            expanded += 1;
+
+            for &(intent, extra_cost) in &moves {
+                self.restore(&nodes[index].snapshot);
+
+                let mut frames = 0;
+                let mut caught = false;
+                for frame in 0..ACTION_FRAMES {
+                    // Dashes are only sent once, then the player slides
+                    let frame_intent = match intent {
+                        Intent::Dash(_) if frame > 0 => Intent::None,
+                        _ => intent,
+                    };
+                    play_frame(&mut self.sim_engine, side, frame_intent);
+                    frames += 1;
+                    if self.sim_engine.state_of_game != StateOfGame::Playing {
+                        break;
+                    }
+                    if self.sim_engine.frisbee.held_by_player == Some(side) {
+                        caught = true;
+                        break;
+                    }
+                }
+                if !caught && self.sim_engine.state_of_game != StateOfGame::Playing {
+                    // A goal was scored before the frisbee could be reached
+                    continue;
+                }
+
+                let cost = nodes[index].cost + frames + extra_cost;
+                let frames = nodes[index].frames + frames;
+                if frames >= MAX_DEPTH || !visited.insert(state_key(&self.sim_engine, side)) {
+                    continue;
+                }
+                let h = if caught { 0 } else { intercept_frames(&self.sim_engine, side) };
+                let child = nodes.len();
+                nodes.push(Node {
+                    snapshot: Snapshot::take(&self.sim_engine),
+                    first_intent: if index == 0 { intent } else { nodes[index].first_intent },
+                    cost,
+                    frames,
+                    caught,
+                });
+                heap.push(Reverse((cost + h, h, child)));
+                if (h, cost, child) < closest {
+                    closest = (h, cost, child);
+                }
+            }
+        }
+
+        nodes[closest.2].first_intent
+    }
+}
+
+impl Default for DijkstraAgent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Agent for DijkstraAgent {
+    fn get_type(&self) -> AgentType {
+        AgentType::Dijkstra
+    }
+
+    fn act(&mut self, side: PlayerSide, engine: &mut GameEngine) -> Intent {
+        if engine.state_of_game != StateOfGame::Playing || player(engine, side).slide.is_some() {
+            return Intent::None;
+        }
+
+        match engine.frisbee.held_by_player {
+            Some(held_by) if held_by == side => self.best_throw(side, engine),
+            None if engine.frisbee.last_held != Some(side) => self.search(side, engine),
+            _ => return_home(engine, side),
+        }
+    }
+}
@@ -0,0 +1,426 @@
+use agent::{ Agent, AgentType, Intent, QVALUES_ACTIONS, action_from_index, human_intent_to_intent };
+use format::{ FileError, write_header, read_header };
+use game_engine::{ GameEngine, EngineRng };
+use player::PlayerSide;
+
+use rand::{ Rng, SeedableRng };
+
+use std::fs::File;
+use std::io::{ Read, Write, BufReader };
+use std::path::Path;
+
+pub const Q_NETWORK_MAGIC: [u8; 4] = *b"RJQN";
+pub const Q_NETWORK_VERSION: u16    = 1;
+
+/// Values describing the state seen by a player, see `observe`
+pub const OBSERVATION_SIZE: usize = 20;
+
+pub type Observation = [f32; OBSERVATION_SIZE];
+
+/// Continuous description of the state seen by `side`, with every value roughly between -1 and 1.
+///
+/// The court is mirrored for the right player like in `linear_q::features`, so that both sides share the same network.
+pub fn observe(engine: &GameEngine, side: PlayerSide) -> Observation {
+    let config = &engine.config;
+    let frisbee = &engine.frisbee;
+    let (own, other, mirror) = match side {
+        PlayerSide::Left => (&engine.players.0, &engine.players.1, 1.0),
+        PlayerSide::Right => (&engine.players.1, &engine.players.0, -1.0),
+    };
+    let flag = |b: bool| if b { 1.0 } else { 0.0 };
+    let held_by_own = frisbee.held_by_player == Some(side);
+    let held_by_other = frisbee.held_by_player.is_some() && !held_by_own;
+
+    let observation = [
+        own.pos.x * mirror / config.wall_ext,
+        own.pos.y / config.wall_vertical,
+        other.pos.x * mirror / config.wall_ext,
+        other.pos.y / config.wall_vertical,
+        frisbee.pos.x * mirror / config.goal_line,
+        frisbee.pos.y / config.wall_vertical,
+        frisbee.direction.x * mirror,
+        frisbee.direction.y,
+        frisbee.speed / config.max_frisbee_speed,
+        // Mirroring the court reverses the spin
+        frisbee.spin * mirror / config.curve_spin,
+        frisbee.height / config.catch_height,
+        flag(held_by_own),
+        flag(held_by_other),
+        flag(frisbee.held_by_player.is_none() && frisbee.last_held.is_some() && frisbee.last_held != Some(side)),
+        flag(own.slide.is_some()),
+        flag(other.slide.is_some()),
+        own.power_time / config.power_throw_window,
+        frisbee.hold_time / config.max_hold_time,
+        engine.time / config.max_round_time,
+        (own.score - other.score) as f64 / config.max_round_points as f64,
+    ];
+
+    let mut res = [0.0; OBSERVATION_SIZE];
+    for (r, o) in res.iter_mut().zip(observation.iter()) {
+        *r = *o as f32;
+    }
+    res
+}
+
+/// Fully connected layer
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Layer {
+    pub inputs:  usize,
+    pub outputs: usize,
+    pub weights: Vec<f32>, // `outputs` rows of `inputs` weights
+    pub biases:  Vec<f32>,
+}
+
+impl Layer {
+    /// He initialization, suited to the ReLU activations
+    fn new<R: Rng>(inputs: usize, outputs: usize, rng: &mut R) -> Self {
+        let limit = (6.0 / inputs as f32).sqrt();
+        Self {
+            inputs,
+            outputs,
+            weights: (0..inputs * outputs).map(|_| rng.gen_range(-limit, limit)).collect(),
+            biases:  vec![0.0; outputs],
+        }
+    }
+
+    fn forward(&self, input: &[f32], relu: bool) -> Vec<f32> {
+        let mut output = self.biases.clone();
+        for (o, out) in output.iter_mut().enumerate() {
+            let row = &self.weights[o * self.inputs..(o + 1) * self.inputs];
+            for (w, x) in row.iter().zip(input.iter()) {
+                *out += w * x;
+            }
+            if relu && *out < 0.0 {
+                *out = 0.0;
+            }
+        }
+        output
+    }
+}
+
+/// Multilayer perceptron giving the Q-value of each action from an observation.
+/// Hidden layers use ReLU, the output layer is linear.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct QNetwork {
+    pub layers: Vec<Layer>, // Empty until trained or loaded
+}
+
+impl QNetwork {
+    /// Randomly initialized network with the given hidden layer sizes
+    pub fn new<R: Rng>(hidden: &[usize], rng: &mut R) -> Self {
+        let mut sizes = vec![OBSERVATION_SIZE];
+        sizes.extend_from_slice(hidden);
+        sizes.push(QVALUES_ACTIONS);
+        Self {
+            layers: sizes.windows(2).map(|w| Layer::new(w[0], w[1], rng)).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Checks that the layers chain from an observation to the Q-values, which `q_values` relies on
+    pub fn validate(&self) -> Result<(), String> {
+        let mut inputs = OBSERVATION_SIZE;
+        for (i, layer) in self.layers.iter().enumerate() {
+            if layer.inputs != inputs {
+                return Err(format!("layer {} has {} inputs instead of {}", i, layer.inputs, inputs));
+            }
+            if layer.weights.len() != layer.inputs * layer.outputs || layer.biases.len() != layer.outputs {
+                return Err(format!("layer {} has {} weights and {} biases for {} inputs and {} outputs",
+                                   i, layer.weights.len(), layer.biases.len(), layer.inputs, layer.outputs));
+            }
+            inputs = layer.outputs;
+        }
+        if inputs != QVALUES_ACTIONS {
+            return Err(format!("the network has {} outputs instead of {}", inputs, QVALUES_ACTIONS));
+        }
+        Ok(())
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), FileError> {
+        write_header(&mut writer, &Q_NETWORK_MAGIC, Q_NETWORK_VERSION)?;
+        ::bincode::serialize_into(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Reads a network, rejecting those whose layers do not fit the observations and actions
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, FileError> {
+        read_header(&mut reader, &Q_NETWORK_MAGIC, Q_NETWORK_VERSION)?;
+        let network: Self = ::bincode::deserialize_from(reader)?;
+        if network.is_empty() {
+            return Err(FileError::Invalid(String::from("the network has no layers")));
+        }
+        network.validate().map_err(FileError::Invalid)?;
+        Ok(network)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        let file = File::open(path)?;
+        Self::read_from(BufReader::new(file))
+    }
+
+    /// Input of each layer, followed by the output of the network
+    fn activations(&self, observation: &Observation) -> Vec<Vec<f32>> {
+        let mut activations = vec![observation.to_vec()];
+        for (i, layer) in self.layers.iter().enumerate() {
+            let output = layer.forward(&activations[i], i + 1 < self.layers.len());
+            activations.push(output);
+        }
+        activations
+    }
+
+    pub fn q_values(&self, observation: &Observation) -> [f32; QVALUES_ACTIONS] {
+        let mut q_values = [0.0; QVALUES_ACTIONS];
+        if self.is_empty() {
+            return q_values;
+        }
+        let activations = self.activations(observation);
+        q_values.copy_from_slice(&activations[activations.len() - 1]);
+        q_values
+    }
+
+    /// Adds the gradient of the loss to `gradients`, given the gradient of the loss on the output
+    fn backward(&self, activations: &[Vec<f32>], output_gradient: Vec<f32>, gradients: &mut [Vec<f32>]) {
+        let mut delta = output_gradient;
+        for (i, layer) in self.layers.iter().enumerate().rev() {
+            let input = &activations[i];
+            let mut input_delta = vec![0.0; layer.inputs];
+            {
+                let (weight_gradients, bias_gradients) = gradients[i].split_at_mut(layer.weights.len());
+                for (o, &d) in delta.iter().enumerate() {
+                    if d == 0.0 {
+                        continue;
+                    }
+                    bias_gradients[o] += d;
+                    let row = o * layer.inputs..(o + 1) * layer.inputs;
+                    for ((g, w), (x, id)) in weight_gradients[row.clone()].iter_mut().zip(layer.weights[row].iter())
+                                                .zip(input.iter().zip(input_delta.iter_mut())) {
+                        *g += d * x;
+                        *id += d * w;
+                    }
+                }
+            }
+            // Through the ReLU of the previous layer, whose outputs are the inputs of this one
+            for (id, x) in input_delta.iter_mut().zip(input.iter()) {
+                if *x <= 0.0 {
+                    *id = 0.0;
+                }
+            }
+            delta = input_delta;
+        }
+    }
+
+    /// Weights then biases of each layer, all at zero
+    fn zero_gradients(&self) -> Vec<Vec<f32>> {
+        self.layers.iter().map(|l| vec![0.0; l.weights.len() + l.biases.len()]).collect()
+    }
+}
+
+/// Adam optimizer state, one moment per parameter
+#[derive(Clone, Serialize, Deserialize)]
+struct Adam {
+    first:  Vec<Vec<f32>>,
+    second: Vec<Vec<f32>>,
+    steps:  i32,
+}
+
+impl Adam {
+    const BETA1: f32 = 0.9;
+    const BETA2: f32 = 0.999;
+    const EPSILON: f32 = 1e-8;
+
+    fn new(network: &QNetwork) -> Self {
+        Self {
+            first:  network.zero_gradients(),
+            second: network.zero_gradients(),
+            steps:  0,
+        }
+    }
+
+    fn step(&mut self, network: &mut QNetwork, gradients: &[Vec<f32>], learning_rate: f32) {
+        self.steps += 1;
+        let correction1 = 1.0 - Self::BETA1.powi(self.steps);
+        let correction2 = 1.0 - Self::BETA2.powi(self.steps);
+        for (i, layer) in network.layers.iter_mut().enumerate() {
+            let weight_count = layer.weights.len();
+            for (j, &g) in gradients[i].iter().enumerate() {
+                let m = &mut self.first[i][j];
+                let v = &mut self.second[i][j];
+                *m = Self::BETA1 * *m + (1.0 - Self::BETA1) * g;
+                *v = Self::BETA2 * *v + (1.0 - Self::BETA2) * g * g;
+                let update = learning_rate * (*m / correction1) / ((*v / correction2).sqrt() + Self::EPSILON);
+                if j < weight_count {
+                    layer.weights[j] -= update;
+                } else {
+                    layer.biases[j - weight_count] -= update;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Transition {
+    pub observation:      Observation,
+    pub action:           usize,
+    pub reward:           f32,
+    pub next_observation: Observation,
+    pub done:             bool, // Nothing is left to expect after this transition
+}
+
+/// Hyperparameters of `DqnTrainer`
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct DqnConfig {
+    pub hidden:          usize, // Neurons of each of the two hidden layers
+    pub learning_rate:   f32,
+    pub discount:        f32,
+    pub reward_scale:    f32, // Rewards are scaled down to keep the Q-values small
+    pub batch_size:      usize,
+    pub replay_capacity: usize,
+    pub train_period:    u64, // Frames between two training batches
+    pub target_period:   u64, // Frames between two copies of the network to the target network
+    pub epsilon_start:   f32,
+    pub epsilon_end:     f32,
+    pub epsilon_frames:  u64, // Frames over which the exploration rate decreases linearly
+}
+
+impl Default for DqnConfig {
+    fn default() -> Self {
+        Self {
+            hidden:          64,
+            learning_rate:   0.0005,
+            discount:        0.99,
+            reward_scale:    0.01,
+            batch_size:      32,
+            replay_capacity: 100_000,
+            train_period:    4,
+            target_period:   2_000,
+            epsilon_start:   1.0,
+            epsilon_end:     0.05,
+            epsilon_frames:  500_000,
+        }
+    }
+}
+
+/// Deep Q-learning with experience replay and a target network.
+///
+/// The transitions of each frame, one per learning side, are pushed together. The network being trained is passed in
+/// so that it can stay in `GameEngine::q_network`, where `DqnAgent` plays with it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DqnTrainer {
+    pub config:  DqnConfig,
+    pub frames:  u64, // Engine frames, whatever the number of learning sides
+    target:      QNetwork,
+    adam:        Adam,
+    replay:      Vec<Transition>,
+    next_replay: usize, // Oldest transition, replaced once the buffer is full
+    rng:         EngineRng,
+}
+
+impl DqnTrainer {
+    /// Trainer of a new network, returned along with it
+    pub fn new(config: DqnConfig, seed: u64) -> (Self, QNetwork) {
+        let mut rng = EngineRng::seed_from_u64(seed);
+        let network = QNetwork::new(&[config.hidden, config.hidden], &mut rng);
+        let trainer = Self::with_network(config, &network, rng);
+        (trainer, network)
+    }
+
+    /// Trainer of an existing network, drawing its batches from `rng`
+    pub fn with_network(config: DqnConfig, network: &QNetwork, rng: EngineRng) -> Self {
+        Self {
+            config,
+            frames:      0,
+            target:      network.clone(),
+            adam:        Adam::new(network),
+            replay:      Vec::with_capacity(config.replay_capacity),
+            next_replay: 0,
+            rng,
+        }
+    }
+
+    /// Exploration rate at the current frame
+    pub fn epsilon(&self) -> f32 {
+        let progress = (self.frames as f32 / self.config.epsilon_frames.max(1) as f32).min(1.0);
+        self.config.epsilon_start + (self.config.epsilon_end - self.config.epsilon_start) * progress
+    }
+
+    /// Stores the transitions of a frame, and trains `network` on a batch of past transitions when it is time to
+    pub fn push_frame(&mut self, transitions: &[Transition], network: &mut QNetwork) {
+        for &transition in transitions {
+            if self.replay.len() < self.config.replay_capacity {
+                self.replay.push(transition);
+            } else {
+                self.replay[self.next_replay] = transition;
+                self.next_replay = (self.next_replay + 1) % self.config.replay_capacity;
+            }
+        }
+
+        self.frames += 1;
+        if self.replay.len() >= self.config.batch_size && self.frames.is_multiple_of(self.config.train_period) {
+            self.train_batch(network);
+        }
+        if self.frames.is_multiple_of(self.config.target_period) {
+            self.target = network.clone();
+        }
+    }
+
+    fn train_batch(&mut self, network: &mut QNetwork) {
+        let mut gradients = network.zero_gradients();
+        let scale = 1.0 / self.config.batch_size as f32;
+        for _ in 0..self.config.batch_size {
+            let transition = self.replay[self.rng.gen_range(0, self.replay.len())];
+            let mut target = transition.reward * self.config.reward_scale;
+            if !transition.done {
+                let next = self.target.q_values(&transition.next_observation);
+                target += self.config.discount * next.iter().cloned().fold(f32::MIN, f32::max);
+            }
+
+            let activations = network.activations(&transition.observation);
+            let q = activations[activations.len() - 1][transition.action];
+            // Gradient of the Huber loss, only on the action taken
+            let mut output_gradient = vec![0.0; QVALUES_ACTIONS];
+            output_gradient[transition.action] = (q - target).clamp(-1.0, 1.0) * scale;
+            network.backward(&activations, output_gradient, &mut gradients);
+        }
+        self.adam.step(network, &gradients, self.config.learning_rate);
+    }
+}
+
+/// Deep Q-Network agent playing with `GameEngine::q_network`, see `observe` for its inputs.
+/// Like `LinearQLearningAgent`, it can play either side.
+pub struct DqnAgent {}
+
+impl Agent for DqnAgent {
+    fn get_type(&self) -> AgentType {
+        AgentType::Dqn
+    }
+
+    fn act(&mut self, side: PlayerSide, engine: &mut GameEngine) -> Intent {
+        let index = if engine.agent_rng.gen_range(0.0, 1.0) < engine.explo_rate {
+            // Explore
+            engine.agent_rng.gen_range(0, QVALUES_ACTIONS)
+        } else {
+            // Exploit
+            let q_values = engine.q_network.q_values(&observe(engine, side));
+            let mut best = 0;
+            for (i, &q) in q_values.iter().enumerate() {
+                if q > q_values[best] {
+                    best = i;
+                }
+            }
+            best
+        };
+        let intent = action_from_index(index, side);
+
+        match side {
+            PlayerSide::Left => engine.inputs.0 = intent,
+            PlayerSide::Right => engine.inputs.1 = intent,
+        };
+
+        human_intent_to_intent(engine, intent, side)
+    }
+}
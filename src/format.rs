@@ -0,0 +1,73 @@
+use std::fmt;
+use std::fs::{ self, File };
+use std::io::{ self, Read, Write };
+use std::path::Path;
+
+/// Error raised when reading or writing one of the engine's binary files (replays, snapshots...).
+#[derive(Debug)]
+pub enum FileError {
+    Io(io::Error),
+    InvalidHeader,
+    UnsupportedVersion(u16),
+    Corrupted(::bincode::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileError::Io(e) => write!(f, "I/O error: {}", e),
+            FileError::InvalidHeader => write!(f, "unrecognized file header"),
+            FileError::UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            FileError::Corrupted(e) => write!(f, "corrupted file: {}", e),
+            FileError::Invalid(e) => write!(f, "invalid file: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for FileError {
+    fn from(e: io::Error) -> Self {
+        FileError::Io(e)
+    }
+}
+
+impl From<::bincode::Error> for FileError {
+    fn from(e: ::bincode::Error) -> Self {
+        FileError::Corrupted(e)
+    }
+}
+
+/// Writes the magic number identifying the kind of file, followed by its format version.
+pub fn write_header<W: Write>(writer: &mut W, magic: &[u8; 4], version: u16) -> Result<(), FileError> {
+    writer.write_all(magic)?;
+    ::bincode::serialize_into(writer, &version)?;
+    Ok(())
+}
+
+/// Checks the header written by `write_header`, rejecting other kinds of files and other versions.
+pub fn read_header<R: Read>(reader: &mut R, magic: &[u8; 4], version: u16) -> Result<(), FileError> {
+    let mut file_magic = [0u8; 4];
+    reader.read_exact(&mut file_magic).map_err(|_| FileError::InvalidHeader)?;
+    if file_magic != *magic {
+        return Err(FileError::InvalidHeader);
+    }
+    let file_version: u16 = ::bincode::deserialize_from(reader).map_err(|_| FileError::InvalidHeader)?;
+    if file_version != version {
+        return Err(FileError::UnsupportedVersion(file_version));
+    }
+    Ok(())
+}
+
+/// Writes a whole file so that readers never see it half-written: the data goes to a temporary
+/// file next to it, which then replaces it.
+pub fn write_atomic<P: AsRef<Path>>(path: P, data: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut tmp_name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
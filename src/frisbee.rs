@@ -1,18 +1,24 @@
+use config::GameConfig;
 use vector2::Vector2;
 use player::PlayerSide;
 
 use rand::Rng;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Frisbee {
     pub pos:            Vector2,
     pub direction:      Vector2,
     pub speed:          f64,
+    pub height:         f64, // Only lobs leave the ground, see `GameConfig::catch_height`
+    pub vertical_speed: f64,
+    pub spin:           f64, // Angle the direction turns by each frame, counterclockwise
+    pub rally:          i8, // Catches since the last point
+    pub hold_time:      f64, // Time the frisbee has been held by the current player
     pub held_by_player: Option<PlayerSide>,
     pub last_held:      Option<PlayerSide>,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ThrowDirection {
     Up = 0,
     LightUp,
@@ -21,8 +27,22 @@ pub enum ThrowDirection {
     Down,
 }
 
-pub fn random_throw_direction() -> ThrowDirection {
-    let mut rng = ::rand::thread_rng();
+/// Side towards which a curve shot bends, see `Player::get_curve`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CurveDirection {
+    Up,
+    Down,
+}
+
+pub fn random_curve_direction<R: Rng>(rng: &mut R) -> CurveDirection {
+    if rng.gen_range(0, 2) == 0 {
+        CurveDirection::Up
+    } else {
+        CurveDirection::Down
+    }
+}
+
+pub fn random_throw_direction<R: Rng>(rng: &mut R) -> ThrowDirection {
     match rng.gen_range(0, 5) {
         0 => ThrowDirection::Up,
         1 => ThrowDirection::LightUp,
@@ -38,8 +58,56 @@ impl Frisbee {
             pos:            Vector2::zero(),
             direction:      Vector2::zero(),
             speed:          0.0,
+            height:         0.0,
+            vertical_speed: 0.0,
+            spin:           0.0,
+            rally:          0,
+            hold_time:      0.0,
             held_by_player: None,
             last_held:      None
         }
     }
+
+    pub fn is_lob(&self) -> bool {
+        self.height > 0.0 || self.vertical_speed != 0.0
+    }
+
+    /// Whether the frisbee flies too high to be caught
+    pub fn is_airborne(&self, config: &GameConfig) -> bool {
+        self.height > config.catch_height
+    }
+
+    pub fn catch(&mut self, side: PlayerSide) {
+        self.held_by_player = Some(side);
+        self.height = 0.0;
+        self.vertical_speed = 0.0;
+        self.spin = 0.0;
+        self.rally = self.rally.saturating_add(1);
+        self.hold_time = 0.0;
+    }
+
+    /// Time left to the player holding the frisbee before it is thrown automatically
+    pub fn hold_time_left(&self, config: &GameConfig) -> f64 {
+        match self.held_by_player {
+            Some(_) => (config.max_hold_time - self.hold_time).max(0.0),
+            None => 0.0,
+        }
+    }
+
+    /// Speed of the next throw, which increases with the length of the rally
+    pub fn throw_speed(&self, power: bool, config: &GameConfig) -> f64 {
+        let mut speed = config.initial_frisbee_speed + self.rally as f64 * config.rally_speed_step;
+        if power {
+            speed *= config.power_throw_factor;
+        }
+        speed.min(config.max_frisbee_speed)
+    }
+
+    pub fn stop(&mut self) {
+        self.direction = Vector2::zero();
+        self.speed = 0.0;
+        self.height = 0.0;
+        self.vertical_speed = 0.0;
+        self.spin = 0.0;
+    }
 }
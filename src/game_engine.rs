@@ -1,38 +1,86 @@
 use vector2::Vector2;
-use frisbee::Frisbee;
+use config::GameConfig;
+use frisbee::{ Frisbee, ThrowDirection };
 use shared_data::SharedData;
 use player::{ Player, PlayerSide };
-use agent::{ Intent, AgentType, Agent, RandomAgent, HumanPlayerAgent, RandomRolloutAgent, DijkstraAgent, TabularQLearningAgent, QValues, HumanIntent, ActionResult };
+use agent::{ Intent, AgentType, AgentConfig, Agent, RandomAgent, HumanPlayerAgent, RandomRolloutAgent, TabularQLearningAgent, HumanIntent, ActionResult };
+use replay::{ Replay, ReplayRecorder, ReplayPlayback };
+use snapshot::{ Snapshot, AgentModel };
+use mcts::MctsAgent;
+use minimax::MinimaxAgent;
+use dijkstra::DijkstraAgent;
+use heuristic::{ HeuristicAgent, Difficulty };
+use linear_q::{ LinearQLearningAgent, QWeights };
+use dqn::{ DqnAgent, QNetwork };
+use q_table::{ QTable, QValues };
+use state_encoder::{ StateEncoder, BasicEncoder, EncoderType };
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::path::{ Path, PathBuf };
+use std::sync::Arc;
+
+use rand::{ Rng, SeedableRng };
+use rand::prng::XorShiftRng;
+
+/// Random number generator owned by the engine. Every random decision taken by the
+/// engine or by the agents must be drawn from one of its streams so that a match can be reproduced from its seed.
+pub type EngineRng = XorShiftRng;
+
+fn rngs_from_seed(seed: u64) -> (EngineRng, EngineRng) {
+    let mut rng = EngineRng::seed_from_u64(seed);
+    let agent_rng = EngineRng::from_rng(&mut rng).expect("Could not seed the agents RNG");
+    (rng, agent_rng)
+}
 
-use rand::Rng;
+/// Log of the engine, next to the game unless `log_path` is changed
+pub const DEFAULT_LOG_PATH: &str = "rustjammers_debug.log";
 
-pub const MAX_ROUND_POINTS: i8       = 30;
-pub const MAX_ROUND_TIME: f64        = 60.0;
-pub const INITIAL_THROW_TIME: f64    = 2.0;
-pub const INITIAL_FRISBEE_SPEED: f64 = 2.5;
-pub const PLAYER_DASH_POWER: f64     = 2.5;
+pub type Agents = (Option<Box<dyn Agent>>, Option<Box<dyn Agent>>);
 
 pub struct GameEngine {
     pub players:       (Player, Player),
-    pub agents:        (Option<Box<Agent>>, Option<Box<Agent>>),
+    pub agents:        Agents,
     pub frisbee:       Frisbee,
     pub time:          f64,
     pub start_time:    f64,
     pub state_of_game: StateOfGame,
+    pub set_number:    i8,
+    pub round_number:  i8, // Round of the current set, including the rounds of its tie-break
+    pub tie_break:     bool,
+    pub config:        GameConfig,
+    pub seed:          u64,
+    pub rng:           EngineRng, // Game rules only, kept apart so that replays can re-drive `step`
+    pub agent_rng:     EngineRng,
+    pub recorder:      Option<ReplayRecorder>,
+    pub playback:      Option<ReplayPlayback>,
+    pub log_path:      Option<PathBuf>, // None disables the log
 
     // Agent-specific fields
     pub inputs:        (HumanIntent, HumanIntent), // Human agent / Q-Learning
-    pub q_values:      QValues, // Q-Learning
+    pub q_values:      Arc<QValues>, // Q-Learning, shared with snapshots
+    pub q_encoder:     EncoderType, // Q-Learning, states of `q_values`
+    pub q_mirror:      bool, // Q-Learning, both sides play the left half of `q_values`, while training with `--mirror`
+    pub q_weights:     Arc<QWeights>, // Linear Q-Learning, shared with snapshots
+    pub q_network:     Arc<QNetwork>, // DQN, shared with snapshots
     pub rewards:       (f32, f32), // Q-Learning
     pub q_scored:      bool, // Q-Learning
     pub explo_rate:    f32, // Q-Learning
 }
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum StateOfGame {
     Start,
     Playing,
     End,
+    SetEnd,
+}
+
+unsafe fn path_from_c_str(path: *const c_char) -> Option<PathBuf> {
+    if path.is_null() {
+        return None;
+    }
+    CStr::from_ptr(path).to_str().ok().map(PathBuf::from)
 }
 
 pub fn state_to_i8(state: &StateOfGame) -> i8 {
@@ -43,42 +91,102 @@ pub fn state_from_i8(state: i8) -> StateOfGame {
     match state {
         0 => StateOfGame::Start,
         1 => StateOfGame::Playing,
+        3 => StateOfGame::SetEnd,
         _ => StateOfGame::End,
     }
 }
 
+/// Throws the frisbee held by `player` as asked by `intent`, which must be a throw, curve or lob
+pub fn throw_frisbee(player: &Player, frisbee: &mut Frisbee, intent: &Intent, config: &GameConfig) -> ActionResult {
+    let power = player.can_power_throw();
+    match intent {
+        Intent::Throw(dir) => {
+            frisbee.direction = player.get_throw_vector(dir);
+            frisbee.speed = frisbee.throw_speed(power, config);
+        },
+        Intent::Curve(dir) => {
+            let (direction, spin) = player.get_curve(dir, config);
+            frisbee.direction = direction;
+            frisbee.spin = spin;
+            frisbee.speed = frisbee.throw_speed(power, config);
+        },
+        Intent::Lob(dir) => {
+            frisbee.direction = player.get_throw_vector(dir);
+            frisbee.speed = config.lob_speed;
+            frisbee.vertical_speed = config.lob_vertical_speed;
+        },
+        _ => return ActionResult::None,
+    }
+    frisbee.last_held = frisbee.held_by_player;
+    frisbee.held_by_player = None;
+    match intent {
+        // Lobs are never power throws
+        Intent::Lob(_) => ActionResult::Threw,
+        _ if power => ActionResult::PowerThrew,
+        _ => ActionResult::Threw,
+    }
+}
+
 impl GameEngine {
     #[no_mangle]
-    pub extern fn initialize() -> *mut Self {
+    pub extern "C" fn initialize() -> *mut Self {
         let boxed = Box::new(Self::new());
         Box::into_raw(boxed)
     }
 
+    #[no_mangle]
+    pub extern "C" fn initialize_with_seed(seed: u64) -> *mut Self {
+        let boxed = Box::new(Self::with_seed(seed));
+        Box::into_raw(boxed)
+    }
+
     pub fn log(&self, s: &str) {
         use std::fs::OpenOptions;
         use std::io::prelude::*;
 
+        let path = match self.log_path {
+            Some(ref path) => path,
+            None => return,
+        };
         let mut file = OpenOptions::new()
             .create(true)
-            .write(true)
             .append(true)
-            .open("rustjammers_debug.log")
+            .open(path)
             .unwrap();
 
         let mut s = String::from(s);
         s.push_str("\r\n");
-        file.write(&s.into_bytes()).unwrap();
+        file.write_all(&s.into_bytes()).unwrap();
         file.flush().unwrap();
     }
 
+    /// Sends the log to `path`, or disables it if `path` is null
+    ///
+    /// # Safety
+    ///
+    /// `path` must be null or a valid null-terminated string.
+    #[no_mangle]
+    pub unsafe extern "C" fn set_log_path(&mut self, path: *const c_char) {
+        self.log_path = path_from_c_str(path);
+    }
+
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `initialize` or `initialize_with_seed`
+    /// and must not be used after this call.
     #[no_mangle]
-    pub unsafe extern fn dispose(ptr: *mut Self) {
+    pub unsafe extern "C" fn dispose(ptr: *mut Self) {
         if !ptr.is_null() {
             let _state: Box<Self> = Box::from_raw(ptr);
         }
     }
 
     pub fn new() -> Self {
+        Self::with_seed(::rand::thread_rng().gen())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        let (rng, agent_rng) = rngs_from_seed(seed);
         Self {
             players: (
                 Player::new(),
@@ -92,137 +200,514 @@ impl GameEngine {
             time: 0.0,
             start_time: 0.0,
             state_of_game: StateOfGame::Start,
+            set_number: 1,
+            round_number: 1,
+            tie_break: false,
+            config: GameConfig::default(),
+            seed,
+            rng,
+            agent_rng,
+            recorder: None,
+            playback: None,
+            log_path: Some(PathBuf::from(DEFAULT_LOG_PATH)),
 
             inputs: (
                 HumanIntent::IDLE,
                 HumanIntent::IDLE,
             ),
-            q_values: QValues::new(),
+            q_values: Arc::new(QValues::default()),
+            q_encoder: EncoderType::Basic,
+            q_mirror: false,
+            q_weights: Arc::new(QWeights::default()),
+            q_network: Arc::new(QNetwork::default()),
             rewards: (0.0, 0.0),
             q_scored: false,
             explo_rate: 0.05,
         }
     }
 
+    #[no_mangle]
+    pub extern "C" fn set_seed(&mut self, seed: u64) {
+        let (rng, agent_rng) = rngs_from_seed(seed);
+        self.seed = seed;
+        self.rng = rng;
+        self.agent_rng = agent_rng;
+    }
+
+    /// Creates a new generator from the agents' one, used to give
+    /// simulated engines their own reproducible random stream.
+    pub fn fork_agent_rng(&mut self) -> EngineRng {
+        EngineRng::from_rng(&mut self.agent_rng).expect("Could not fork the agents RNG")
+    }
+
+    /// Copies the state of the game into an engine used for simulations.
+    /// Agents too slow to be simulated are replaced by random agents.
     pub fn copy_in(&self, new_game_engine: &mut GameEngine) {
-        new_game_engine.players = self.players;
-        new_game_engine.agents = (
-            Some(Box::new(RandomAgent {})),
-            Some(Box::new(RandomAgent {}))
-        );
-        new_game_engine.frisbee = self.frisbee;
-        new_game_engine.inputs = self.inputs;
-        new_game_engine.time = self.time;
-        new_game_engine.start_time = self.start_time;
-        new_game_engine.state_of_game = self.state_of_game;
+        Snapshot::take(self).restore_with(new_game_engine, (AgentModel::Rollout, AgentModel::Rollout));
+    }
+
+    #[no_mangle]
+    pub extern "C" fn get_game_config(&self) -> GameConfig {
+        self.config
+    }
+
+    /// Replaces the current config. Start positions and the round timer are applied at the next `reset`.
+    /// Invalid configs are rejected and logged.
+    #[no_mangle]
+    pub extern "C" fn set_game_config(&mut self, config: GameConfig) -> bool {
+        match config.validate() {
+            Ok(_) => {
+                self.config = config;
+                true
+            },
+            Err(e) => {
+                self.log(&format!("Invalid game config: {}", e));
+                false
+            }
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `path` must be a valid null-terminated string.
+    #[no_mangle]
+    pub unsafe extern "C" fn load_game_config(&mut self, path: *const c_char) -> bool {
+        let path = match path_from_c_str(path) {
+            Some(path) => path,
+            None => return false,
+        };
+        match GameConfig::load(&path) {
+            Ok(config) => {
+                self.config = config;
+                true
+            },
+            Err(e) => {
+                self.log(&format!("Could not load game config \"{}\": {}", path.display(), e));
+                false
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::take(self)
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        snapshot.restore(self);
+    }
+
+    /// # Safety
+    ///
+    /// `path` must be a valid null-terminated string.
+    #[no_mangle]
+    pub unsafe extern "C" fn save_snapshot(&self, path: *const c_char) -> bool {
+        let path = match path_from_c_str(path) {
+            Some(path) => path,
+            None => return false,
+        };
+        match self.snapshot().save(&path) {
+            Ok(_) => true,
+            Err(e) => {
+                self.log(&format!("Could not save snapshot \"{}\": {}", path.display(), e));
+                false
+            }
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `path` must be a valid null-terminated string.
+    #[no_mangle]
+    pub unsafe extern "C" fn load_snapshot(&mut self, path: *const c_char) -> bool {
+        let path = match path_from_c_str(path) {
+            Some(path) => path,
+            None => return false,
+        };
+        match Snapshot::load(&path) {
+            Ok(snapshot) => {
+                self.restore(&snapshot);
+                if snapshot.agents.0.agent_type == AgentType::TabularQLearning ||
+                   snapshot.agents.1.agent_type == AgentType::TabularQLearning {
+                    self.load_q_values();
+                }
+                if snapshot.agents.0.agent_type == AgentType::LinearQLearning ||
+                   snapshot.agents.1.agent_type == AgentType::LinearQLearning {
+                    self.load_q_weights();
+                }
+                if snapshot.agents.0.agent_type == AgentType::Dqn ||
+                   snapshot.agents.1.agent_type == AgentType::Dqn {
+                    self.load_q_network();
+                }
+                true
+            },
+            Err(e) => {
+                self.log(&format!("Could not load snapshot \"{}\": {}", path.display(), e));
+                false
+            }
+        }
     }
 
-    fn create_agent_from_type(agent_type: AgentType, frames: f64, sim: i8) -> Box<Agent> {
-        match agent_type {
+    pub fn create_agent(config: AgentConfig) -> Box<dyn Agent> {
+        match config.agent_type {
             AgentType::Random =>           Box::new(RandomAgent {}),
             AgentType::HumanPlayer =>      Box::new(HumanPlayerAgent {}),
-            AgentType::RandomRollout =>    Box::new(RandomRolloutAgent {frames: frames,sim: sim}),
-            AgentType::Dijkstra =>         Box::new(DijkstraAgent {}),
+            AgentType::RandomRollout =>    Box::new(RandomRolloutAgent { frames: config.frames, sim: config.sim }),
+            AgentType::Dijkstra =>         Box::new(DijkstraAgent::new()),
             AgentType::TabularQLearning => Box::new(TabularQLearningAgent {}),
+            AgentType::Mcts =>             Box::new(MctsAgent::new(config.frames, config.sim, config.time_budget)),
+            AgentType::Minimax =>          Box::new(MinimaxAgent::new(config.sim, config.time_budget)),
+            AgentType::HeuristicEasy =>    Box::new(HeuristicAgent::new(Difficulty::Easy)),
+            AgentType::HeuristicMedium =>  Box::new(HeuristicAgent::new(Difficulty::Medium)),
+            AgentType::HeuristicHard =>    Box::new(HeuristicAgent::new(Difficulty::Hard)),
+            AgentType::LinearQLearning =>  Box::new(LinearQLearningAgent {}),
+            AgentType::Dqn =>              Box::new(DqnAgent {}),
             AgentType::None =>             panic!("Invalid agent type."),
         }
     }
 
     #[no_mangle]
-    pub extern fn reset(&mut self) {
-        self.players.0.pos.x = -9.0;
-        self.players.0.pos.y = 0.0;
+    pub extern "C" fn reset(&mut self) {
+        self.reset_set();
+        self.frisbee.last_held = None;
+        self.players.0.sets = 0;
+        self.players.1.sets = 0;
+        self.set_number = 1;
+
+        self.inputs = (HumanIntent::IDLE, HumanIntent::IDLE);
+        self.rewards = (0.0, 0.0);
+        self.q_scored = false;
+
+        self.playback = None;
+        if self.recorder.is_some() {
+            // Start every recorded match from a known seed
+            let seed = self.rng.gen();
+            self.set_seed(seed);
+            if let Some(ref mut recorder) = self.recorder {
+                recorder.replay = Some(Replay::new(seed, self.config));
+            }
+        }
+    }
+
+    /// Puts the players and the frisbee back in place for a new set.
+    fn reset_set(&mut self) {
+        self.reset_positions();
         self.players.0.score = 0;
+        self.players.1.score = 0;
+
+        self.time = self.config.max_round_time;
+        self.start_time = 0.0;
+        self.round_number = 1;
+        self.tie_break = false;
+
+        self.state_of_game = StateOfGame::Start;
+    }
+
+    /// Puts the players and the frisbee back in place for a kickoff, keeping the scores.
+    fn reset_positions(&mut self) {
+        self.players.0.pos.x = -self.config.player_start_x;
+        self.players.0.pos.y = 0.0;
         self.players.0.side = Some(PlayerSide::Left);
+        self.players.0.slide = None;
+        self.players.0.power_time = 0.0;
 
-        self.players.1.pos.x = 9.0;
+        self.players.1.pos.x = self.config.player_start_x;
         self.players.1.pos.y = 0.0;
-        self.players.1.score = 0;
         self.players.1.side = Some(PlayerSide::Right);
+        self.players.1.slide = None;
+        self.players.1.power_time = 0.0;
 
         self.frisbee.pos.x = 0.0;
-        self.frisbee.pos.y = -4.0;
+        self.frisbee.pos.y = self.config.frisbee_start_y;
         self.frisbee.direction.x = 0.0;
         self.frisbee.direction.y = 0.0;
         self.frisbee.speed = 0.0;
-        self.frisbee.last_held = None;
+        self.frisbee.height = 0.0;
+        self.frisbee.vertical_speed = 0.0;
+        self.frisbee.spin = 0.0;
+        self.frisbee.rally = 0;
+        self.frisbee.hold_time = 0.0;
         self.frisbee.held_by_player = None;
+    }
 
-        self.time = MAX_ROUND_TIME;
-        self.start_time = 0.0;
+    /// Ends the current set if a player reached the maximum score, broke a tie,
+    /// or if the last round ran out of time with a leader.
+    /// Other rounds running out of time start the next one, and a tie after the last round starts a sudden death,
+    /// played in rounds of `tie_break_time` until a point is scored, so that every set has a single winner.
+    fn check_end_of_set(&mut self) {
+        let scores = (self.players.0.score, self.players.1.score);
+        let leader = if scores.0 > scores.1 {
+            Some(PlayerSide::Left)
+        } else if scores.1 > scores.0 {
+            Some(PlayerSide::Right)
+        } else {
+            None
+        };
+        let last_round = self.tie_break || self.round_number >= self.config.rounds_per_set;
+        let max_points = scores.0 >= self.config.max_round_points || scores.1 >= self.config.max_round_points;
+
+        let winner = match leader {
+            Some(side) if max_points || self.tie_break || (last_round && self.time <= 0.0) => side,
+            _ => {
+                if self.time <= 0.0 {
+                    if last_round {
+                        self.tie_break = true;
+                        self.round_number += 1;
+                        self.time = self.config.tie_break_time;
+                    } else {
+                        self.start_next_round();
+                    }
+                }
+                return;
+            },
+        };
 
+        match winner {
+            PlayerSide::Left => self.players.0.sets += 1,
+            PlayerSide::Right => self.players.1.sets += 1,
+        }
+
+        let sets_to_win = self.config.sets_to_win();
+        if self.players.0.sets >= sets_to_win || self.players.1.sets >= sets_to_win {
+            self.state_of_game = StateOfGame::End;
+        } else {
+            self.state_of_game = StateOfGame::SetEnd;
+            self.start_time = 0.0;
+            // The loser of the set receives the first throw of the next one
+            self.frisbee.last_held = Some(winner);
+        }
+    }
+
+    /// Starts the next timed round of the set from the kickoff positions, the scores adding up
+    fn start_next_round(&mut self) {
+        self.reset_positions();
+        self.time = self.config.max_round_time;
+        self.start_time = 0.0;
+        self.round_number += 1;
         self.state_of_game = StateOfGame::Start;
+    }
 
-        self.inputs = (HumanIntent::IDLE, HumanIntent::IDLE);
-        self.rewards = (0.0, 0.0);
-        self.q_scored = false;
+    fn start_next_set(&mut self) {
+        self.reset_set();
+        self.set_number += 1;
+    }
+
+    /// Records every match starting from the next `reset` and saves it to `path` when it ends.
+    pub fn record_replays_to<P: Into<PathBuf>>(&mut self, path: P) {
+        self.recorder = Some(ReplayRecorder {
+            path: path.into(),
+            replay: None,
+        });
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.recorder = None;
+    }
+
+    /// # Safety
+    ///
+    /// `path` must be a valid null-terminated string.
+    #[no_mangle]
+    pub unsafe extern "C" fn record_replays(&mut self, path: *const c_char) {
+        if let Some(path) = path_from_c_str(path) {
+            self.record_replays_to(path);
+        }
+    }
+
+    /// Replaces the current match with the one stored in `replay`,
+    /// subsequent calls to `epoch` will play it back frame by frame.
+    /// `replay` must have agents on both sides, as checked by `Replay::read_from`.
+    pub fn start_playback(&mut self, replay: Replay) {
+        self.config = replay.config;
+        self.reset();
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.replay = None;
+        }
+        self.set_seed(replay.seed);
+        self.agents = (
+            Some(Self::create_agent(AgentConfig::new(replay.agent_types.0))),
+            Some(Self::create_agent(AgentConfig::new(replay.agent_types.1))),
+        );
+        self.playback = Some(ReplayPlayback {
+            replay,
+            frame: 0,
+        });
+    }
+
+    /// # Safety
+    ///
+    /// `path` must be a valid null-terminated string.
+    #[no_mangle]
+    pub unsafe extern "C" fn load_replay(&mut self, path: *const c_char) -> bool {
+        let path = match path_from_c_str(path) {
+            Some(path) => path,
+            None => return false,
+        };
+        match Replay::load(&path) {
+            Ok(replay) => {
+                self.start_playback(replay);
+                true
+            },
+            Err(e) => {
+                self.log(&format!("Could not load replay \"{}\": {}", path.display(), e));
+                false
+            }
+        }
+    }
+
+    fn record_frame(&mut self, agent_types: (AgentType, AgentType), intents: (Intent, Intent)) {
+        let inputs = self.inputs;
+        if let Some(ref mut recorder) = self.recorder {
+            if let Some(ref mut replay) = recorder.replay {
+                if replay.frames.is_empty() {
+                    replay.agent_types = agent_types;
+                }
+                replay.push(inputs, intents);
+            }
+        }
     }
 
+    fn save_recording(&mut self) {
+        let res = match self.recorder {
+            Some(ref mut recorder) => match recorder.replay.take() {
+                Some(replay) => replay.save(&recorder.path).map_err(|e| (recorder.path.clone(), e)),
+                None => Ok(()),
+            },
+            None => Ok(()),
+        };
+        if let Err((path, e)) = res {
+            self.log(&format!("Could not save replay \"{}\": {}", path.display(), e));
+        }
+    }
+
+    /// Sets the agent of the left player, see `AgentConfig` for the parameters
     #[no_mangle]
-    pub extern fn send_type_p1(&mut self, agent_type: i8, frames: f64, sim: i8) {
+    pub extern "C" fn send_type_p1(&mut self, agent_type: i8, frames: f64, sim: i8, time_budget: f64) {
         let t = ::agent::agent_type_from_i8(agent_type);
-        self.agents.0 = Some(Self::create_agent_from_type(t, frames, sim));
+        self.agents.0 = Some(Self::create_agent(AgentConfig { agent_type: t, frames, sim, time_budget }));
         if t == AgentType::TabularQLearning {
             self.load_q_values();
         }
+        if t == AgentType::LinearQLearning {
+            self.load_q_weights();
+        }
+        if t == AgentType::Dqn {
+            self.load_q_network();
+        }
     }
 
+    /// Sets the agent of the right player, see `AgentConfig` for the parameters
     #[no_mangle]
-    pub extern fn send_type_p2(&mut self, agent_type: i8, frames: f64, sim: i8) {
+    pub extern "C" fn send_type_p2(&mut self, agent_type: i8, frames: f64, sim: i8, time_budget: f64) {
         let t = ::agent::agent_type_from_i8(agent_type);
-        self.agents.1 = Some(Self::create_agent_from_type(t, frames, sim));
+        self.agents.1 = Some(Self::create_agent(AgentConfig { agent_type: t, frames, sim, time_budget }));
         if t == AgentType::TabularQLearning {
             self.load_q_values();
         }
+        if t == AgentType::LinearQLearning {
+            self.load_q_weights();
+        }
+        if t == AgentType::Dqn {
+            self.load_q_network();
+        }
     }
 
     fn load_q_values(&mut self) {
         if !self.q_values.is_empty() {
             return;
         }
-        use ::std::fs::File;
-        use ::std::io::BufReader;
+        if !Path::new("q_values.bin").exists() {
+            return;
+        }
+        match QTable::load_for("q_values.bin", self) {
+            Ok(table) => {
+                self.q_values = table.q_values;
+                self.q_encoder = table.header.encoder;
+                // Saved tables have both halves, see `--mirror`
+                self.q_mirror = false;
+            },
+            Err(e) => self.log(&format!("Could not load Q-values: {}", e)),
+        }
+    }
 
-        let f = match File::open("q_values.bin") {
-            Ok(f) => f,
-            _ => return,
-        };
-        let br = BufReader::new(f);
-        self.q_values = ::bincode::deserialize_from(br).unwrap();
+    fn load_q_weights(&mut self) {
+        if !self.q_weights.is_empty() {
+            return;
+        }
+        if !Path::new("q_weights.bin").exists() {
+            return;
+        }
+        match QWeights::load("q_weights.bin") {
+            Ok(q_weights) => self.q_weights = Arc::new(q_weights),
+            Err(e) => self.log(&format!("Could not load Q-weights: {}", e)),
+        }
+    }
+
+    fn load_q_network(&mut self) {
+        if !self.q_network.is_empty() {
+            return;
+        }
+        if !Path::new("q_network.bin").exists() {
+            return;
+        }
+        match QNetwork::load("q_network.bin") {
+            Ok(q_network) => self.q_network = Arc::new(q_network),
+            Err(e) => self.log(&format!("Could not load Q-network: {}", e)),
+        }
     }
 
     #[no_mangle]
-    pub extern fn epoch(&mut self, p1_h_action: HumanIntent, p2_h_action: HumanIntent) {
-        let mut a1 = self.agents.0.take().unwrap();
-        let mut a2 = self.agents.1.take().unwrap();
+    pub extern "C" fn epoch(&mut self, p1_h_action: HumanIntent, p2_h_action: HumanIntent) {
+        if let Some(mut playback) = self.playback.take() {
+            if let Some(frame) = playback.next_frame() {
+                self.inputs = frame.human_intents();
+                self.step(frame.intents);
+            }
+            self.playback = Some(playback);
+            return;
+        }
+
+        let agent_types = (
+            self.agents.0.as_ref().unwrap().get_type(),
+            self.agents.1.as_ref().unwrap().get_type(),
+        );
 
-        let input1 = match a1.get_type() {
+        let input1 = match agent_types.0 {
             AgentType::HumanPlayer => p1_h_action,
             _ => HumanIntent::IDLE
         };
 
-        let input2 = match a2.get_type() {
+        let input2 = match agent_types.1 {
             AgentType::HumanPlayer => p2_h_action,
             _ => HumanIntent::IDLE
         };
 
         self.inputs = (input1, input2);
+
+        // Only the acting agent is taken out of the engine so that it can still see its opponent
+        let mut a1 = self.agents.0.take().unwrap();
         let action_p1 = a1.act(PlayerSide::Left, self);
+        self.agents.0 = Some(a1);
+
+        let mut a2 = self.agents.1.take().unwrap();
         let action_p2 = a2.act(PlayerSide::Right, self);
+        self.agents.1 = Some(a2);
 
-        self.agents = (
-            Some(a1),
-            Some(a2)
-        );
+        if self.state_of_game != StateOfGame::End {
+            self.record_frame(agent_types, (action_p1, action_p2));
+        }
 
         self.step((
             action_p1,
             action_p2
         ));
+
+        if self.state_of_game == StateOfGame::End {
+            self.save_recording();
+        }
     }
 
     #[no_mangle]
-    pub extern fn get_state(&mut self) -> SharedData {
+    pub extern "C" fn get_state(&mut self) -> SharedData {
         let mut data = SharedData::new();
         self.to_shared_data(&mut data);
         data
@@ -230,24 +715,29 @@ impl GameEngine {
 
     pub fn step(&mut self, intents: (Intent, Intent)) {
         // Update timers
-        let time_step = 1.0 / 60.0; // Assume we run at 60 frames per second
-        self.time -= time_step;
+        let time_step = self.config.time_step;
         self.start_time += time_step;
 
-        // End game if one of the players reached the maximum score
-        // or if the time runs out
-        if self.players.0.score >= MAX_ROUND_POINTS ||
-           self.players.1.score >= MAX_ROUND_POINTS ||
-           self.time <= 0.0 {
-           self.state_of_game = StateOfGame::End;
+        if self.state_of_game == StateOfGame::SetEnd {
+            // Wait a bit before starting the next set
+            if self.start_time >= self.config.set_break_time {
+                self.start_next_set();
+            }
+            return;
         }
         if self.state_of_game == StateOfGame::End {
-            // We don't need to update the rest if the game just ended
+            return;
+        }
+        self.time -= time_step;
+
+        self.check_end_of_set();
+        if self.state_of_game == StateOfGame::End || self.state_of_game == StateOfGame::SetEnd {
+            // We don't need to update the rest if the set just ended
             return;
         }
 
         // Start the round after waiting a bit for players to reset their positions
-        if self.state_of_game == StateOfGame::Start && self.start_time >= 1.0 {
+        if self.state_of_game == StateOfGame::Start && self.start_time >= self.config.kickoff_delay {
             // Resume the game
             self.state_of_game = StateOfGame::Playing;
 
@@ -261,8 +751,7 @@ impl GameEngine {
                     }
                 },
                 None => {
-                    let mut rng = ::rand::thread_rng();
-                    if rng.gen_range(0.0, 1.0) < 0.5 {
+                    if self.rng.gen_range(0.0, 1.0) < 0.5 {
                         self.frisbee.last_held = Some(PlayerSide::Right);
                         &self.players.0
                     } else {
@@ -273,68 +762,66 @@ impl GameEngine {
             };
             // Set direction so that the frisbee arrives in the player's hands
             self.frisbee.direction = (target.pos + Vector2::new(target.get_horizontal_aim_direction(), 0.0) - self.frisbee.pos).normalized();
-            self.frisbee.speed = INITIAL_FRISBEE_SPEED;
+            self.frisbee.speed = self.config.initial_frisbee_speed;
         }
 
-        fn apply_action(player: &mut Player, frisbee: &mut Frisbee, intent: &Intent, state_of_game: &StateOfGame) -> ActionResult {
+        fn apply_action(player: &mut Player, frisbee: &mut Frisbee, intent: &Intent, state_of_game: &StateOfGame, config: &GameConfig) -> ActionResult {
             let mut res = ActionResult::None;
 
             match intent {
                 Intent::None => {},
                 Intent::Move(dir) => {
-                    if *state_of_game == StateOfGame::Playing {
-                        if player.slide.is_none() { // Cannot move while dashing
-                            match frisbee.held_by_player {
-                                // Cannot move while holding frisbee
-                                Some(held_by) if held_by == player.side.unwrap() => {},
-                                _ => {
-                                    player.pos += *dir * 0.1;
-                                    res = ActionResult::Moved;
-                                }
-                            };
-                        }
+                    // Cannot move while dashing
+                    if *state_of_game == StateOfGame::Playing && player.slide.is_none() {
+                        match frisbee.held_by_player {
+                            // Cannot move while holding frisbee
+                            Some(held_by) if held_by == player.side.unwrap() => {},
+                            _ => {
+                                player.pos += *dir * config.player_move_speed;
+                                res = ActionResult::Moved;
+                            }
+                        };
                     }
                 },
                 Intent::Dash(dir) => {
                     if *state_of_game == StateOfGame::Playing {
                         let dir = dir.normalized();
-                        player.dash(dir * PLAYER_DASH_POWER);
+                        player.dash(dir * config.player_dash_power);
                         res = ActionResult::Dashed;
                     }
                 },
-                Intent::Throw(dir) => {
+                Intent::Throw(_) | Intent::Curve(_) | Intent::Lob(_) => {
                     match frisbee.held_by_player {
                         Some(held_by) if held_by == player.side.unwrap() => {
-                            frisbee.direction = player.get_throw_vector(dir);
-                            frisbee.speed = INITIAL_FRISBEE_SPEED;
-                            frisbee.last_held = frisbee.held_by_player;
-                            frisbee.held_by_player = None;
-                            res = ActionResult::Threw;
+                            res = throw_frisbee(player, frisbee, intent, config);
                         },
                         _ => {}
                     };
-                }
+                },
             };
 
+            if player.slide.is_some() {
+                let slide = player.slide.unwrap();
+                player.pos += slide.dir * config.player_dash_speed;
+                if slide.has_reached_goal(&player.pos) {
+                    player.pos = slide.target;
+                    player.end_slide(config);
+                }
+            } else {
+                player.power_time = (player.power_time - config.time_step).max(0.0);
+            }
             if *state_of_game == StateOfGame::Playing {
                 // We check the state of game to prevent grabbing the frisbee before it is initially thrown (Start state)
                 match frisbee.held_by_player {
-                    None if ::collision::player_collides_with_frisbee(player, frisbee) => {
-                        // Grab frisbee if the player collides with it
-                        frisbee.held_by_player = player.side;
+                    None if ::collision::player_collides_with_frisbee(player, frisbee, config) => {
+                        // Grab frisbee if the player collides with it, which stops the dash
+                        frisbee.catch(player.side.unwrap());
+                        player.end_slide(config);
                         res = ActionResult::GrabbedFrisbee;
                     },
                     _ => {}
                 };
             }
-            if player.slide.is_some() {
-                let slide = player.slide.unwrap();
-                player.pos += slide.dir * 4.0 * 0.1;
-                if slide.has_reached_goal(&player.pos) {
-                    player.pos = slide.target;
-                    player.slide = None;
-                }
-            }
 
             res
         }
@@ -349,6 +836,11 @@ impl GameEngine {
                     *reward = 0.0;
                     return true;
                 },
+                ActionResult::PowerThrew => {
+                    // Power throws are harder to catch
+                    *reward = 1.0;
+                    return true;
+                },
             };
             false
         }
@@ -358,15 +850,11 @@ impl GameEngine {
             let a2 = engine.agents.1.take().unwrap();
 
             match engine.frisbee.last_held {
-                Some(PlayerSide::Left) => {
-                    if a2.get_type() == AgentType::TabularQLearning {
-                        engine.rewards.1 = -100.0;
-                    }
+                Some(PlayerSide::Left) if a2.get_type().is_q_learning() => {
+                    engine.rewards.1 = -100.0;
                 },
-                Some(PlayerSide::Right) => {
-                    if a1.get_type() == AgentType::TabularQLearning {
-                        engine.rewards.0 = -100.0;
-                    }
+                Some(PlayerSide::Right) if a1.get_type().is_q_learning() => {
+                    engine.rewards.0 = -100.0;
                 },
                 _ => {}
             };
@@ -375,20 +863,41 @@ impl GameEngine {
             engine.q_scored = true;
         }
 
+        // Players cannot keep the frisbee forever
+        let mut intents = intents;
+        if self.frisbee.held_by_player.is_some() && self.frisbee.hold_time >= self.config.max_hold_time {
+            match self.frisbee.held_by_player {
+                Some(PlayerSide::Left) => intents.0 = Intent::Throw(ThrowDirection::Middle),
+                Some(PlayerSide::Right) => intents.1 = Intent::Throw(ThrowDirection::Middle),
+                None => {},
+            }
+        }
+
         let a1 = self.agents.0.take().unwrap();
-        let res = apply_action(&mut self.players.0, &mut self.frisbee, &intents.0, &self.state_of_game);
-        if a1.get_type() == AgentType::TabularQLearning {
+        let res = apply_action(&mut self.players.0, &mut self.frisbee, &intents.0, &self.state_of_game, &self.config);
+        if a1.get_type().is_q_learning() {
             apply_action_rewards_to_q_agent(res, &mut self.rewards.0);
         }
 
         let a2 = self.agents.1.take().unwrap();
-        let res = apply_action(&mut self.players.1, &mut self.frisbee, &intents.1, &self.state_of_game);
-        if a2.get_type() == AgentType::TabularQLearning {
+        let res = apply_action(&mut self.players.1, &mut self.frisbee, &intents.1, &self.state_of_game, &self.config);
+        if a2.get_type().is_q_learning() {
             apply_action_rewards_to_q_agent(res, &mut self.rewards.1);
         }
 
         self.agents = (Some(a1), Some(a2));
 
+        let collided = ::collision::player_collision(&mut self.players.0, &self.config);
+        if collided {
+            // Cancels slide if the player hits an obstacle to prevent being stuck
+            self.players.0.end_slide(&self.config);
+        }
+        let collided = ::collision::player_collision(&mut self.players.1, &self.config);
+        if collided {
+            // Cancels slide if the player hits an obstacle to prevent being stuck
+            self.players.1.end_slide(&self.config);
+        }
+
         match self.frisbee.held_by_player {
             Some(held_by) => {
                 match held_by {
@@ -396,32 +905,40 @@ impl GameEngine {
                     PlayerSide::Left => self.frisbee.pos = self.players.0.pos,
                     PlayerSide::Right => self.frisbee.pos = self.players.1.pos
                 };
+                self.frisbee.hold_time += time_step;
             },
             None => {
+                if self.frisbee.spin != 0.0 {
+                    self.frisbee.direction = self.frisbee.direction.rotated(self.frisbee.spin);
+                }
+                let mut caught_by = None;
                 if self.frisbee.speed != 0.0 {
-                    self.frisbee.pos += self.frisbee.direction * self.frisbee.speed * 0.1;
+                    // Swept against the players and walls so that fast throws cannot go through them
+                    let can_catch = self.state_of_game == StateOfGame::Playing;
+                    caught_by = ::collision::move_frisbee(&mut self.frisbee, &self.players, &self.config, can_catch);
+                }
+                if self.frisbee.is_lob() {
+                    self.frisbee.height += self.frisbee.vertical_speed;
+                    self.frisbee.vertical_speed -= self.config.gravity;
+                }
+                if let Some(side) = caught_by {
+                    self.frisbee.catch(side);
+                    match side {
+                        PlayerSide::Left => self.players.0.end_slide(&self.config),
+                        PlayerSide::Right => self.players.1.end_slide(&self.config),
+                    };
                 }
             },
         };
 
-        let collided = ::collision::player_collision(&mut self.players.0);
-        if collided {
-            // Cancels slide if the player hits an obstacle to prevent being stuck
-            self.players.0.slide = None;
-        }
-        let collided = ::collision::player_collision(&mut self.players.1);
-        if collided {
-            // Cancels slide if the player hits an obstacle to prevent being stuck
-            self.players.1.slide = None;
-        }
-
-        ::collision::frisbee_collision_wall(&mut self.frisbee);
-        let goal = ::collision::frisbee_collision_goal(&mut self.frisbee, &mut self.players);
+        let goal = ::collision::frisbee_collision_goal(&mut self.frisbee, &mut self.players, &self.config) ||
+                   ::collision::frisbee_collision_ground(&mut self.frisbee, &mut self.players, &self.config);
         if goal {
+            self.frisbee.rally = 0;
             self.state_of_game = StateOfGame::Start;
             self.start_time = 0.0;
-            self.players.0.dash_to_pos(Vector2::new(-9.0, 0.0));
-            self.players.1.dash_to_pos(Vector2::new(9.0, 0.0));
+            self.players.0.dash_to_pos(Vector2::new(-self.config.player_start_x, 0.0));
+            self.players.1.dash_to_pos(Vector2::new(self.config.player_start_x, 0.0));
 
             reward_q_for_goal(self);
         }
@@ -441,74 +958,45 @@ impl GameEngine {
         shared.zbee_x = self.frisbee.pos.x;
         shared.zbee_y = self.frisbee.pos.y;
         shared.zbee_held = ::player::player_side_to_i8(self.frisbee.held_by_player);
+        shared.zbee_height = self.frisbee.height;
+        shared.zbee_speed = self.frisbee.speed;
+        shared.rally = self.frisbee.rally;
+        shared.hold_time_left = self.frisbee.hold_time_left(&self.config);
 
         shared.time = self.time;
 
         shared.state_of_game = state_to_i8(&self.state_of_game);
+
+        shared.p1_sets = self.players.0.sets;
+        shared.p2_sets = self.players.1.sets;
+        shared.set_number = self.set_number;
+        shared.round_number = self.round_number;
+        shared.tie_break = self.tie_break as i8;
     }
 
+    /// State seen by the left player with the basic encoding, see `BasicEncoder`
     pub fn hash(&self) -> u64 {
-        fn set_state(hash: &mut u64, val: f64, min: i64, max: i64, scale: f64, amplitudes: &mut Vec<u32>, max_value: &mut u64) {
-            fn discretize(val: f64, min: i64, max: i64, scale: f64) -> (u32, u32) {
-                let min = min as f64 * scale;
-                let max = max as f64 * scale;
-                let val = val * scale;
-                let amplitude = (max - min + 1.0) as u32;
-                let res = ((val.round() + min.abs()) as u32) % amplitude;
-                (res, amplitude)
-            }
-
-            let mut factor = 1;
-            for a in amplitudes.iter() {
-                factor *= *a;
-            }
-            let (val, _) = discretize(val, min, max, scale);
-            *hash += (val * factor) as u64;
-            let (val, amplitude) = discretize(max as f64, min, max, scale);
-            *max_value += (val * factor) as u64;
-            amplitudes.push(amplitude);
-        }
-
-        fn discretize_frisbee_direction(v: Vector2) -> f64 {
-            fn angle(v: Vector2) -> f64 {
-                (v.y / v.x.abs()).sin().to_degrees()
-            }
-
-            let a = angle(v);
-            if a > 40.0 {
-                0.0
-            } else if a > 10.0 {
-                1.0
-            } else if a < 10.0 && a > -10.0 {
-                2.0
-            } else if a < -10.0 && a > -40.0 {
-                3.0
-            } else {
-                4.0
-            }
-        }
-
-        let mut val = 0;
-        let mut max_value = 0;
-        let mut amplitudes: Vec<u32> = Vec::new();
-
-        let scale = 1.0;
-        set_state(&mut val, self.players.0.pos.x, -9, -1, scale, &mut amplitudes, &mut max_value);
-        set_state(&mut val, self.players.0.pos.y, -5, 5, scale, &mut amplitudes, &mut max_value);
+        BasicEncoder.encode(self, PlayerSide::Left)
+    }
 
-        set_state(&mut val, self.frisbee.pos.x, -9, 9, scale, &mut amplitudes, &mut max_value);
-        set_state(&mut val, self.frisbee.pos.y, -5, 5, scale, &mut amplitudes, &mut max_value);
+    /// Same as `hash`, for the state seen by `side` on the court mirrored for the right player
+    pub fn hash_for(&self, side: PlayerSide) -> u64 {
+        BasicEncoder.encode(self, side)
+    }
 
-        set_state(&mut val, match self.frisbee.last_held {
-            Some(side) => match side {
-                PlayerSide::Left => 1.0,
-                PlayerSide::Right => 0.0,
-            },
-            None => 0.0
-        }, 0, 1, 1.0, &mut amplitudes, &mut max_value);
+    /// Number of distinct values `hash` can return with the current config.
+    pub fn hash_size(&self) -> u64 {
+        BasicEncoder.state_count(self)
+    }
 
-        set_state(&mut val, discretize_frisbee_direction(self.frisbee.direction), 0, 4, 1.0, &mut amplitudes, &mut max_value);
+    /// State seen by `side` with the encoding of `q_values`
+    pub fn q_state(&self, side: PlayerSide) -> u64 {
+        self.q_encoder.encoder().encode(self, side)
+    }
+}
 
-        val
+impl Default for GameEngine {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -1,11 +1,42 @@
 extern crate rustjammers_engine;
 extern crate bincode;
+extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 
 use rustjammers_engine::agent;
-use rustjammers_engine::game_engine;
+use rustjammers_engine::format::{ self, FileError };
+use rustjammers_engine::game_engine::{ self, EngineRng };
+use rustjammers_engine::linear_q;
+use rustjammers_engine::dqn;
+use rustjammers_engine::q_table::{ self, QTable, QTableHeader, QValues, TrainingInfo };
+use rustjammers_engine::player::PlayerSide;
+use rustjammers_engine::state_encoder::{ self, EncoderType };
+use rustjammers_engine::td_learning::{ self, TdAlgorithm, TdLearner };
+
+use rand::Rng;
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{ Path, PathBuf };
+use std::sync::Arc;
+
+/// Simulations between two copies of the learner added to the opponent pool
+const POOL_PERIOD: u32 = 1000;
+/// Probability for the learner to face the opponent pool rather than itself, once there is a pool
+const POOL_RATE: f32 = 0.5;
+/// Exploration of the past versions of the learner
+const POOL_EXPLO_RATE: f32 = 0.05;
+
+/// Points each worker plays between two averages of the parameters, when training on several threads
+const SYNC_EPISODES: u32 = 500;
+
+const CHECKPOINT_MAGIC: [u8; 4] = *b"RJCK";
+const CHECKPOINT_VERSION: u16 = 6;
 
 fn max(arr: &[f32; agent::QVALUES_ACTIONS]) -> f32 {
-    let mut max = std::f32::MIN;
+    let mut max = f32::MIN;
     for x in arr {
         if max < *x {
             max = *x;
@@ -25,7 +56,7 @@ fn progress_bar(count: i32, total: i32, text_current: &str, text_total: &str) {
     print!("\r[{}] {}% ({} / {}) ", bar, percents, text_current, text_total);
 
     if count >= total {
-        println!("");
+        println!();
     }
 }
 
@@ -43,124 +74,1123 @@ fn seconds_to_string(mut seconds: u64) -> String {
     res
 }
 
-enum RunMode {
-    Iterations,
-    Time,
+/// Action index and reward of `side` in the last frame
+fn last_action(engine: &game_engine::GameEngine, side: PlayerSide) -> (usize, f32) {
+    match side {
+        PlayerSide::Left => (agent::action_index(engine.inputs.0, side), engine.rewards.0),
+        PlayerSide::Right => (agent::action_index(engine.inputs.1, side), engine.rewards.1),
+    }
 }
 
-fn main() {
-    let mut run_mode = RunMode::Iterations;
-    let mut n = 50_000;
-    let mut duration_seconds: u64 = 0;
-    let start_time = std::time::Instant::now();
-    if let Some(arg) = std::env::args().nth(1) {
-        if arg.contains(':') {
-            run_mode = RunMode::Time;
-            let mut split: Vec<&str> = arg.split(':').collect();
-            split.reverse();
-            if split.len() > 0 {
-                duration_seconds += split[0].parse::<u64>().unwrap();
-            }
-            if split.len() > 1 {
-                duration_seconds += split[1].parse::<u64>().unwrap() * 60;
+/// Plays a frame and gives the action of each learning side to the learner.
+/// With `mirror`, both sides share the left half of the table.
+fn tabular_step(engine: &mut game_engine::GameEngine, learner: &mut TdLearner, learners: &[PlayerSide], mirror: bool) {
+    let states: Vec<u64> = learners.iter().map(|&side| engine.q_state(side)).collect();
+
+    engine.epoch(::agent::HumanIntent::IDLE, ::agent::HumanIntent::IDLE);
+
+    for (&side, &state) in learners.iter().zip(states.iter()) {
+        let (action, reward) = last_action(engine, side);
+        let right_half = side == PlayerSide::Right && !mirror;
+        learner.observe(engine, side, right_half, state, action, reward);
+    }
+}
+
+/// Plays a frame and updates the Q-weights of the action of each learning side
+fn linear_step(engine: &mut game_engine::GameEngine, learning_rate: f32, discounting_rate: f32, learners: &[PlayerSide]) {
+    let features: Vec<linear_q::Features> = learners.iter().map(|&side| linear_q::features(engine, side)).collect();
+
+    engine.epoch(::agent::HumanIntent::IDLE, ::agent::HumanIntent::IDLE);
+
+    for (&side, features) in learners.iter().zip(features.iter()) {
+        let (action, reward) = last_action(engine, side);
+
+        // Update the weights, nothing is left to expect after a goal
+        let mut target = reward;
+        if !engine.q_scored {
+            target += discounting_rate * max(&engine.q_weights.q_values(&linear_q::features(engine, side)));
+        }
+        let delta = target - engine.q_weights.q_values(features)[action];
+        Arc::make_mut(&mut engine.q_weights).update(features, action, delta, learning_rate);
+    }
+}
+
+/// Plays a frame, stores the transitions of the learning sides and trains the network from time to time
+fn dqn_step(engine: &mut game_engine::GameEngine, trainer: &mut dqn::DqnTrainer, learners: &[PlayerSide]) {
+    let observations: Vec<dqn::Observation> = learners.iter().map(|&side| dqn::observe(engine, side)).collect();
+    engine.explo_rate = trainer.epsilon();
+
+    engine.epoch(::agent::HumanIntent::IDLE, ::agent::HumanIntent::IDLE);
+
+    let transitions: Vec<dqn::Transition> = learners.iter().zip(observations.iter()).map(|(&side, &observation)| {
+        let (action, reward) = last_action(engine, side);
+        dqn::Transition {
+            observation,
+            action,
+            reward,
+            next_observation: dqn::observe(engine, side),
+            done: engine.q_scored,
+        }
+    }).collect();
+    trainer.push_frame(&transitions, Arc::make_mut(&mut engine.q_network));
+}
+
+/// Parameters of every learning agent, shared with the engine until it updates them
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct Parameters {
+    q_values:  Arc<QValues>,
+    q_weights: Arc<linear_q::QWeights>,
+    q_network: Arc<dqn::QNetwork>,
+}
+
+impl Parameters {
+    fn take(engine: &game_engine::GameEngine) -> Self {
+        Self {
+            q_values:  engine.q_values.clone(),
+            q_weights: engine.q_weights.clone(),
+            q_network: engine.q_network.clone(),
+        }
+    }
+
+    fn swap(&mut self, engine: &mut game_engine::GameEngine) {
+        std::mem::swap(&mut self.q_values, &mut engine.q_values);
+        std::mem::swap(&mut self.q_weights, &mut engine.q_weights);
+        std::mem::swap(&mut self.q_network, &mut engine.q_network);
+    }
+}
+
+/// Past version of the learner, playing with the parameters it had then
+struct PoolAgent {
+    agent:      Box<dyn agent::Agent>,
+    parameters: Parameters,
+}
+
+impl agent::Agent for PoolAgent {
+    fn get_type(&self) -> agent::AgentType {
+        self.agent.get_type()
+    }
+
+    fn act(&mut self, side: PlayerSide, engine: &mut game_engine::GameEngine) -> agent::Intent {
+        let explo_rate = engine.explo_rate;
+        engine.explo_rate = POOL_EXPLO_RATE;
+        self.parameters.swap(engine);
+        let intent = self.agent.act(side, engine);
+        self.parameters.swap(engine);
+        engine.explo_rate = explo_rate;
+        intent
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+enum Algorithm {
+    Tabular,
+    Linear,
+    Dqn,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Progress {
+    Bar,
+    Lines, // One line per percent, for logs
+    Quiet,
+}
+
+const USAGE: &str = "Usage: generate_qvalues [options]
+
+Training:
+  --algorithm <name>        tabular, linear or dqn (default: tabular)
+  --episodes <count>        Points to play (default: 50000, or no limit with --duration)
+  --duration <[[HH:]MM:]SS> Stops after this time
+  --seed <number>           Seed of the training engines (default: random)
+  --threads <count>         Engines training in parallel, their parameters averaged every 500 points each (default: 1)
+  --output <path>           File the result is written to (default: next to the Unity project)
+
+Checkpoints:
+  --checkpoint <path>       File the training state is saved to (default: the output path followed by .checkpoint)
+  --checkpoint-interval <[[HH:]MM:]SS>
+                            Time between two checkpoints, 0 for none (default: 05:00)
+  --resume                  Continues the training saved in the checkpoint
+
+Opponent:
+  --opponent <agent>        Agent faced by the learner, as name[:frames[:sim[:time budget]]] (default: random)
+  --self-play               The learner plays against itself and learns from both sides
+  --pool <size>             Self-play in which half of the points are played against past versions of the learner
+  --mirror                  tabular: both sides share one table on a mirrored court
+  --storage <kind>          tabular: sparse, allocating states on their first visit, or dense, faster (default: sparse)
+  --encoder <name>          tabular: basic, or full with the opponent and the holder of the frisbee (default: basic)
+  --update <rule>           tabular: q-learning, sarsa, expected-sarsa or double-q (default: q-learning)
+  --n-steps <count>         tabular: rewards summed before bootstrapping on the value of a state (default: 1)
+  --trace-decay <rate>      tabular: decay of the eligibility traces, 0 for none (default: 0)
+
+Hyperparameters:
+  --learning-rate <rate>    Default: 0.8 (tabular), 0.1 (linear), 0.0005 (dqn)
+  --discount <rate>         Default: 0.95, 0.99 for dqn
+  --explo-start <rate>      Exploration rate of the first point (default: 1.0)
+  --explo-end <rate>        Lowest exploration rate (default: 0.05)
+  --explo-decay <rate>      tabular and linear: exponential decay of the exploration rate per point (default: 0.0025)
+  --explo-frames <count>    dqn: frames over which the exploration rate decreases linearly (default: 500000)
+  --hidden <size>           dqn: neurons of each hidden layer (default: 64)
+  --batch-size <size>       dqn: transitions per training batch (default: 32)
+  --replay-capacity <size>  dqn: transitions kept for experience replay (default: 100000)
+  --train-period <frames>   dqn: frames between two training batches (default: 4)
+  --target-period <frames>  dqn: frames between two updates of the target network (default: 2000)
+  --reward-scale <factor>   dqn: factor applied to the rewards (default: 0.01)
+
+Output:
+  --progress <mode>         bar, or lines for logs (default: bar)
+  --quiet                   Only prints errors
+  --help                    Prints this message";
+
+/// Command line of the trainer
+struct Options {
+    algorithm:     Algorithm,
+    episodes:      Option<u32>,
+    duration:      Option<u64>, // In seconds
+    seed:          Option<u64>,
+    threads:       usize,
+    output:        Option<PathBuf>,
+    checkpoint:    Option<PathBuf>,
+    checkpoint_interval: u64, // In seconds
+    resume:        bool,
+    opponent:      agent::AgentConfig,
+    self_play:     bool,
+    pool_size:     usize,
+    mirror:        bool,
+    dense:         bool,
+    encoder:       EncoderType,
+    td:            td_learning::TdConfig, // Learning rate and discount are set from the options above
+    learning_rate: Option<f32>,
+    discount:      Option<f32>,
+    explo_start:   f32,
+    explo_end:     f32,
+    explo_decay:   f32,
+    dqn:           dqn::DqnConfig, // Learning rate, discount and exploration are set from the options above
+    progress:      Progress,
+}
+
+/// Parses `[[HH:]MM:]SS` into seconds
+fn parse_duration(arg: &str) -> Result<u64, String> {
+    let error = || format!("Invalid duration \"{}\", expected [[HH:]MM:]SS", arg);
+    let parts: Vec<&str> = arg.split(':').collect();
+    if parts.len() > 3 {
+        return Err(error());
+    }
+    let mut seconds: u64 = 0;
+    for (i, part) in parts.iter().enumerate() {
+        let value: u64 = part.parse().map_err(|_| error())?;
+        // Only the first part can exceed its unit
+        if i > 0 && value >= 60 {
+            return Err(error());
+        }
+        seconds = seconds.checked_mul(60).and_then(|seconds| seconds.checked_add(value)).ok_or_else(error)?;
+    }
+    Ok(seconds)
+}
+
+fn parse_value<T: std::str::FromStr>(option: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("Missing value for {}", option))?;
+    value.parse().map_err(|_| format!("Invalid value \"{}\" for {}", value, option))
+}
+
+/// Numbers other than infinities and NaN
+fn parse_finite(option: &str, value: Option<String>) -> Result<f32, String> {
+    let value: f32 = parse_value(option, value)?;
+    if !value.is_finite() {
+        return Err(format!("{} must be a finite number", option));
+    }
+    Ok(value)
+}
+
+/// Rates between 0 and 1
+fn parse_rate(option: &str, value: Option<String>) -> Result<f32, String> {
+    let rate = parse_finite(option, value)?;
+    if !(0.0..=1.0).contains(&rate) {
+        return Err(format!("{} must be between 0 and 1", option));
+    }
+    Ok(rate)
+}
+
+fn parse_positive<T: std::str::FromStr + PartialOrd + Default>(option: &str, value: Option<String>) -> Result<T, String> {
+    let value: T = parse_value(option, value)?;
+    if value <= T::default() {
+        return Err(format!("{} must be positive", option));
+    }
+    Ok(value)
+}
+
+/// Finite numbers above 0
+fn parse_positive_finite(option: &str, value: Option<String>) -> Result<f32, String> {
+    let value = parse_finite(option, value)?;
+    if value <= 0.0 {
+        return Err(format!("{} must be positive", option));
+    }
+    Ok(value)
+}
+
+/// Options only read by the tabular agent
+const TABULAR_OPTIONS: &[&str] = &["--mirror", "--storage", "--encoder", "--update", "--n-steps", "--trace-decay"];
+/// Options only read by the DQN agent
+const DQN_OPTIONS: &[&str] = &["--explo-frames", "--hidden", "--batch-size", "--replay-capacity", "--train-period", "--target-period", "--reward-scale"];
+
+impl Options {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut options = Self {
+            algorithm:     Algorithm::Tabular,
+            episodes:      None,
+            duration:      None,
+            seed:          None,
+            threads:       1,
+            output:        None,
+            checkpoint:    None,
+            checkpoint_interval: 300,
+            resume:        false,
+            opponent:      agent::AgentConfig::new(agent::AgentType::Random),
+            self_play:     false,
+            pool_size:     0,
+            mirror:        false,
+            dense:         false,
+            encoder:       EncoderType::Basic,
+            td:            td_learning::TdConfig::default(),
+            learning_rate: None,
+            discount:      None,
+            explo_start:   1.0,
+            explo_end:     0.05,
+            explo_decay:   0.0025,
+            dqn:           dqn::DqnConfig::default(),
+            progress:      Progress::Bar,
+        };
+        let mut opponent_set = false;
+        let mut tabular_option = None;
+        let mut dqn_option = None;
+        let mut explo_decay_set = false;
+
+        while let Some(arg) = args.next() {
+            if TABULAR_OPTIONS.contains(&arg.as_str()) {
+                tabular_option = Some(arg.clone());
             }
-            if split.len() > 2 {
-                duration_seconds += split[2].parse::<u64>().unwrap() * 60 * 60;
+            if DQN_OPTIONS.contains(&arg.as_str()) {
+                dqn_option = Some(arg.clone());
             }
-        } else {
-            run_mode = RunMode::Iterations;
-            match arg.parse() {
-                Ok(arg) => n = arg,
-                Err(_) => {
-                    eprintln!("Could not parse the number of simulations.");
-                    std::process::exit(1);
-                }
-            };
-            if n <= 0 {
-                eprintln!("Incorrect number of simulations.");
-                std::process::exit(1);
+            match arg.as_str() {
+                "--algorithm" => options.algorithm = match args.next().as_deref() {
+                    Some("tabular") => Algorithm::Tabular,
+                    Some("linear") => Algorithm::Linear,
+                    Some("dqn") => Algorithm::Dqn,
+                    Some(name) => return Err(format!("Unknown algorithm \"{}\", expected tabular, linear or dqn", name)),
+                    None => return Err(String::from("Missing value for --algorithm")),
+                },
+                "--episodes" => options.episodes = Some(parse_positive(&arg, args.next())?),
+                "--duration" => {
+                    let value = args.next().ok_or_else(|| String::from("Missing value for --duration"))?;
+                    options.duration = Some(parse_duration(&value)?);
+                },
+                "--seed" => options.seed = Some(parse_value(&arg, args.next())?),
+                "--threads" => options.threads = parse_positive(&arg, args.next())?,
+                "--output" => options.output = Some(PathBuf::from(args.next().ok_or_else(|| String::from("Missing value for --output"))?)),
+                "--checkpoint" => options.checkpoint = Some(PathBuf::from(args.next().ok_or_else(|| String::from("Missing value for --checkpoint"))?)),
+                "--checkpoint-interval" => {
+                    let value = args.next().ok_or_else(|| String::from("Missing value for --checkpoint-interval"))?;
+                    options.checkpoint_interval = parse_duration(&value)?;
+                },
+                "--resume" => options.resume = true,
+                "--opponent" => {
+                    let value = args.next().ok_or_else(|| String::from("Missing value for --opponent"))?;
+                    options.opponent = agent::parse_agent(&value).ok_or_else(|| format!("Invalid opponent \"{}\"", value))?;
+                    opponent_set = true;
+                },
+                "--self-play" => options.self_play = true,
+                "--pool" => {
+                    options.pool_size = parse_positive(&arg, args.next())?;
+                    options.self_play = true;
+                },
+                "--mirror" => options.mirror = true,
+                "--storage" => options.dense = match args.next().as_deref() {
+                    Some("sparse") => false,
+                    Some("dense") => true,
+                    Some(kind) => return Err(format!("Unknown storage \"{}\", expected sparse or dense", kind)),
+                    None => return Err(String::from("Missing value for --storage")),
+                },
+                "--encoder" => {
+                    let value = args.next().ok_or_else(|| String::from("Missing value for --encoder"))?;
+                    options.encoder = state_encoder::encoder_type_from_name(&value)
+                        .ok_or_else(|| format!("Unknown encoder \"{}\", expected basic or full", value))?;
+                },
+                "--update" => {
+                    let value = args.next().ok_or_else(|| String::from("Missing value for --update"))?;
+                    options.td.algorithm = td_learning::td_algorithm_from_name(&value)
+                        .ok_or_else(|| format!("Unknown update rule \"{}\", expected q-learning, sarsa, expected-sarsa or double-q", value))?;
+                },
+                "--n-steps" => options.td.n_steps = parse_positive(&arg, args.next())?,
+                "--trace-decay" => options.td.trace_decay = parse_rate(&arg, args.next())?,
+                "--learning-rate" => options.learning_rate = Some(parse_positive_finite(&arg, args.next())?),
+                "--discount" => options.discount = Some(parse_rate(&arg, args.next())?),
+                "--explo-start" => options.explo_start = parse_rate(&arg, args.next())?,
+                "--explo-end" => options.explo_end = parse_rate(&arg, args.next())?,
+                "--explo-decay" => {
+                    options.explo_decay = parse_finite(&arg, args.next())?;
+                    explo_decay_set = true;
+                },
+                "--explo-frames" => options.dqn.epsilon_frames = parse_positive(&arg, args.next())?,
+                "--hidden" => options.dqn.hidden = parse_positive(&arg, args.next())?,
+                "--batch-size" => options.dqn.batch_size = parse_positive(&arg, args.next())?,
+                "--replay-capacity" => options.dqn.replay_capacity = parse_positive(&arg, args.next())?,
+                "--train-period" => options.dqn.train_period = parse_positive(&arg, args.next())?,
+                "--target-period" => options.dqn.target_period = parse_positive(&arg, args.next())?,
+                "--reward-scale" => options.dqn.reward_scale = parse_positive_finite(&arg, args.next())?,
+                "--progress" => options.progress = match args.next().as_deref() {
+                    Some("bar") => Progress::Bar,
+                    Some("lines") => Progress::Lines,
+                    Some(mode) => return Err(format!("Unknown progress mode \"{}\", expected bar or lines", mode)),
+                    None => return Err(String::from("Missing value for --progress")),
+                },
+                "--quiet" => options.progress = Progress::Quiet,
+                "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                },
+                _ => return Err(format!("Unknown argument \"{}\"", arg)),
             }
         }
+
+        if options.self_play && opponent_set {
+            return Err(String::from("--opponent cannot be used with --self-play or --pool"));
+        }
+        if let Some(option) = tabular_option.filter(|_| options.algorithm != Algorithm::Tabular) {
+            return Err(format!("{} can only be used with --algorithm tabular", option));
+        }
+        if let Some(option) = dqn_option.filter(|_| options.algorithm != Algorithm::Dqn) {
+            return Err(format!("{} can only be used with --algorithm dqn", option));
+        }
+        if explo_decay_set && options.algorithm == Algorithm::Dqn {
+            return Err(String::from("--explo-decay cannot be used with --algorithm dqn, see --explo-frames"));
+        }
+        if options.explo_end > options.explo_start {
+            return Err(String::from("--explo-end must not be greater than --explo-start"));
+        }
+        if options.explo_decay < 0.0 {
+            return Err(String::from("--explo-decay must not be negative"));
+        }
+        if options.td.n_steps > 1 && options.td.trace_decay > 0.0 {
+            return Err(String::from("--n-steps cannot be used with --trace-decay"));
+        }
+        if options.episodes.is_none() && options.duration.is_none() {
+            options.episodes = Some(50_000);
+        }
+
+        options.td.learning_rate = options.learning_rate.unwrap_or(0.8);
+        options.td.discount = options.discount.unwrap_or(0.95);
+        options.dqn.epsilon_start = options.explo_start;
+        options.dqn.epsilon_end = options.explo_end;
+        if let Some(learning_rate) = options.learning_rate {
+            options.dqn.learning_rate = learning_rate;
+        }
+        if let Some(discount) = options.discount {
+            options.dqn.discount = discount;
+        }
+        Ok(options)
     }
+}
 
-    let mut engine = game_engine::GameEngine::new();
-    let discounting_rate = 0.95f32;
-    let learning_rate = 0.8f32;
+/// State of a worker saved in checkpoints, its parameters apart
+#[derive(Serialize, Deserialize)]
+struct WorkerState {
+    seed:      u64,
+    rng:       EngineRng,
+    agent_rng: EngineRng,
+    trainer:   Option<dqn::DqnTrainer>,
+    second:    Option<Arc<QValues>>, // Of Double Q-learning
+}
 
-    let min_explo_rate: f32 = 0.05;
-    let max_explo_rate: f32 = 1.0;
-    let explo_decay_rate: f32 = 0.0025;
+/// Training state saved from time to time, from which `--resume` continues
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    algorithm:  Algorithm,
+    encoder:    EncoderType, // Of the tabular agent
+    update:     TdAlgorithm, // Of the tabular agent
+    dense:      bool, // Of the tabular agent
+    seed:       u64,
+    opponent:   agent::AgentConfig,
+    self_play:  bool,
+    pool_size:  usize,
+    mirror:     bool,
+    episodes:   u32, // Points played
+    elapsed:    u64, // Seconds of training
+    parameters: Parameters,
+    pool:       Vec<Parameters>,
+    workers:    Vec<WorkerState>,
+}
 
-    engine.send_type_p1(agent::AgentType::TabularQLearning as i8, 0.0, 0);
-    //engine.send_type_p2(agent::AgentType::TabularQLearning as i8);
-    engine.send_type_p2(agent::AgentType::Random as i8, 0.0, 0);
+impl Checkpoint {
+    fn new(options: &Options, seed: u64, episodes: u32, elapsed: u64, workers: &[Worker], pool: &[Parameters]) -> Self {
+        Self {
+            algorithm:  options.algorithm,
+            encoder:    options.encoder,
+            update:     options.td.algorithm,
+            dense:      options.dense,
+            seed,
+            opponent:   options.opponent,
+            self_play:  options.self_play,
+            pool_size:  options.pool_size,
+            mirror:     options.mirror,
+            episodes,
+            elapsed,
+            parameters: Parameters::take(&workers[0].engine),
+            pool:       pool.to_vec(),
+            workers:    workers.iter().map(Worker::state).collect(),
+        }
+    }
 
-    println!("Initializing table...");
-    engine.q_values = agent::get_blank_q_values();
-    engine.explo_rate = 1.0;
+    fn save(&self, path: &Path) -> Result<(), FileError> {
+        let mut data = Vec::new();
+        format::write_header(&mut data, &CHECKPOINT_MAGIC, CHECKPOINT_VERSION)?;
+        bincode::serialize_into(&mut data, self)?;
+        format::write_atomic(path, &data)?;
+        Ok(())
+    }
 
-    println!("Starting simulations...");
-    let mut i = 0;
-    loop {
-        match run_mode {
-            RunMode::Iterations => {
-                if i >= n {
-                    break;
-                }
+    fn load(path: &Path) -> Result<Self, FileError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        format::read_header(&mut reader, &CHECKPOINT_MAGIC, CHECKPOINT_VERSION)?;
+        Ok(bincode::deserialize_from(reader)?)
+    }
+
+    /// Options of the command line that would not continue the same training
+    fn check_options(&self, options: &Options) -> Result<(), String> {
+        if self.algorithm != options.algorithm {
+            return Err(format!("was saved by the {:?} algorithm", self.algorithm));
+        }
+        if self.encoder != options.encoder {
+            return Err(format!("was saved with the {:?} encoder", self.encoder));
+        }
+        if self.update != options.td.algorithm {
+            return Err(format!("was saved with the {:?} update rule", self.update));
+        }
+        if self.dense != options.dense {
+            return Err(format!("was saved with --storage {}", if self.dense { "dense" } else { "sparse" }));
+        }
+        // Otherwise the points would not be shared out in the same way
+        if self.workers.len() != options.threads {
+            return Err(format!("was saved with --threads {}", self.workers.len()));
+        }
+        // The seed can be left out, in which case the one of the checkpoint is kept
+        if options.seed.is_some_and(|seed| seed != self.seed) {
+            return Err(format!("was saved with --seed {}", self.seed));
+        }
+        if self.self_play != options.self_play || self.pool_size != options.pool_size {
+            return Err(match (self.self_play, self.pool_size) {
+                (false, _) => String::from("was saved without --self-play"),
+                (true, 0) => String::from("was saved with --self-play"),
+                (true, pool_size) => format!("was saved with --pool {}", pool_size),
+            });
+        }
+        if !self.self_play && self.opponent != options.opponent {
+            return Err(format!("was saved against {:?}", self.opponent));
+        }
+        if self.mirror != options.mirror {
+            return Err(String::from(if self.mirror { "was saved with --mirror" } else { "was saved without --mirror" }));
+        }
+        Ok(())
+    }
+}
+
+/// Agent being trained
+enum Learner {
+    Tabular(TdLearner),
+    Linear,
+    Dqn(Box<dqn::DqnTrainer>),
+}
+
+/// Settings shared by the workers
+struct Training<'a> {
+    options:          &'a Options,
+    learner_type:     agent::AgentType,
+    learning_rate:    f32,
+    discounting_rate: f32,
+    pool:             &'a [Parameters],
+}
+
+/// Engine playing training points, on its own thread when there are several
+struct Worker {
+    engine:  game_engine::GameEngine,
+    learner: Learner,
+}
+
+impl Worker {
+    /// Worker starting from the `parameters` of the learner
+    fn new(seed: u64, options: &Options, learner_type: agent::AgentType, parameters: &Parameters) -> Self {
+        let mut engine = game_engine::GameEngine::with_seed(seed);
+        engine.send_type_p1(learner_type as i8, 0.0, 0, 0.0);
+        if options.self_play {
+            engine.send_type_p2(learner_type as i8, 0.0, 0, 0.0);
+        } else {
+            let opponent = options.opponent;
+            engine.send_type_p2(opponent.agent_type as i8, opponent.frames, opponent.sim, opponent.time_budget);
+        }
+
+        // The opponent may play with the parameters loaded by `send_type_p2`
+        let learner = match options.algorithm {
+            Algorithm::Tabular => {
+                engine.q_values = parameters.q_values.clone();
+                engine.q_encoder = options.encoder;
+                engine.q_mirror = options.mirror;
+                Learner::Tabular(TdLearner::new(options.td, &engine.q_values))
+            },
+            Algorithm::Linear => {
+                engine.q_weights = parameters.q_weights.clone();
+                Learner::Linear
+            },
+            Algorithm::Dqn => {
+                engine.q_network = parameters.q_network.clone();
+                let rng = engine.fork_agent_rng();
+                Learner::Dqn(Box::new(dqn::DqnTrainer::with_network(options.dqn, &engine.q_network, rng)))
             },
-            RunMode::Time => {
-                if start_time.elapsed().as_secs() >= duration_seconds {
-                    break;
-                }
-            }
         };
+        Self { engine, learner }
+    }
 
-        engine.reset();
-        while engine.state_of_game != game_engine::StateOfGame::End {
-            let state = engine.hash();
+    fn state(&self) -> WorkerState {
+        WorkerState {
+            seed:      self.engine.seed,
+            rng:       self.engine.rng.clone(),
+            agent_rng: self.engine.agent_rng.clone(),
+            trainer:   match self.learner {
+                Learner::Dqn(ref trainer) => Some((**trainer).clone()),
+                _ => None,
+            },
+            second:    match self.learner {
+                Learner::Tabular(ref learner) => learner.second.clone(),
+                _ => None,
+            },
+        }
+    }
+
+    fn restore(&mut self, state: WorkerState) {
+        self.engine.seed = state.seed;
+        self.engine.rng = state.rng;
+        self.engine.agent_rng = state.agent_rng;
+        match (&mut self.learner, state.trainer, state.second) {
+            (Learner::Dqn(ref mut trainer), Some(saved), _) => **trainer = saved,
+            (Learner::Tabular(ref mut learner), _, Some(second)) => learner.second = Some(second),
+            _ => {},
+        };
+    }
 
-            engine.epoch(::agent::HumanIntent::IDLE, ::agent::HumanIntent::IDLE);
-            let actions = engine.inputs;
-            let actions = (
-                ::agent::human_intent_to_index(actions.0) as usize,
-                /*::agent::human_intent_to_index(actions.1) as usize,*/
-            );
+    /// Plays the `episode`-th point of the training
+    fn play(&mut self, episode: u32, training: &Training) {
+        let options = training.options;
+        let engine = &mut self.engine;
 
-            // Update Q-Values
-            let new_state = engine.hash();
-            engine.q_values.get_mut(&state).unwrap().0[actions.0] = engine.q_values[&state].0[actions.0] + learning_rate * (engine.rewards.0 + discounting_rate * max(&engine.q_values[&new_state].0) - engine.q_values[&state].0[actions.0]);
-            //engine.q_values.get_mut(&state).unwrap().1[actions.1] = engine.q_values[&state].1[actions.1] + learning_rate * (engine.rewards.1 + discounting_rate * max(&engine.q_values[&new_state].1) - engine.q_values[&state].1[actions.1]);
+        // The DQN trainer decreases the exploration rate every frame instead
+        match self.learner {
+            Learner::Dqn(_) => {},
+            _ => engine.explo_rate = options.explo_end + (options.explo_start - options.explo_end) * (-options.explo_decay * episode as f32).exp(),
+        };
+
+        // In self-play, the right player learns too unless it is a past version of the learner
+        let mut learners = vec![PlayerSide::Left];
+        if options.self_play {
+            let opponent = game_engine::GameEngine::create_agent(agent::AgentConfig::new(training.learner_type));
+            let pool = training.pool;
+            if !pool.is_empty() && engine.agent_rng.gen_range(0.0, 1.0) < POOL_RATE {
+                let parameters = pool[engine.agent_rng.gen_range(0, pool.len())].clone();
+                engine.agents.1 = Some(Box::new(PoolAgent { agent: opponent, parameters }));
+            } else {
+                engine.agents.1 = Some(opponent);
+                learners.push(PlayerSide::Right);
+            }
+        }
+
+        engine.reset();
+        if let Learner::Tabular(ref mut learner) = self.learner {
+            learner.start_point();
+        }
+        while engine.state_of_game != game_engine::StateOfGame::End {
+            match self.learner {
+                Learner::Tabular(ref mut learner) => tabular_step(engine, learner, &learners, options.mirror),
+                Learner::Linear => linear_step(engine, training.learning_rate, training.discounting_rate, &learners),
+                Learner::Dqn(ref mut trainer) => dqn_step(engine, trainer, &learners),
+            };
 
             if engine.q_scored {
                 break;
             }
         }
-        // Update exploration rate
-        engine.explo_rate = min_explo_rate + (max_explo_rate - min_explo_rate) * (-explo_decay_rate * i as f32).exp();
+        if let Learner::Tabular(ref mut learner) = self.learner {
+            learner.finish_point(engine);
+        }
+    }
+}
 
-        i += 1;
+fn add_scaled(sum: &mut [f32], values: &[f32], scale: f32) {
+    for (s, v) in sum.iter_mut().zip(values.iter()) {
+        *s += v * scale;
+    }
+}
 
-        match run_mode {
-            RunMode::Iterations => progress_bar(i, n, &format!("{}", i), &format!("{}", n)),
-            RunMode::Time => {
-                let secs = start_time.elapsed().as_secs();
-                progress_bar(secs as i32, duration_seconds as i32, &seconds_to_string(secs), &seconds_to_string(duration_seconds))
-            },
-        };
+/// Mean of the tables of the workers, see `average`
+fn mean_q_values(tables: &[&Arc<QValues>]) -> Arc<QValues> {
+    let scale = 1.0 / tables.len() as f32;
+    let mut mean = tables[0].clone();
+    let q_values = Arc::make_mut(&mut mean);
+    for entry in q_values.values_mut() {
+        entry.0.iter_mut().chain(entry.1.iter_mut()).for_each(|q| *q *= scale);
+    }
+    // Some states may only have been visited by some of the workers
+    for other in &tables[1..] {
+        for (state, values) in other.iter() {
+            let entry = q_values.entry(state);
+            add_scaled(&mut entry.0, &values.0, scale);
+            add_scaled(&mut entry.1, &values.1, scale);
+        }
+    }
+    mean
+}
+
+/// Mean of the parameters trained by the workers, which all get a copy of it.
+/// Each value is summed in the order of the workers, so that the result only depends on the seed and the number of threads.
+fn average(workers: &mut [Worker], algorithm: Algorithm) {
+    let parameters: Vec<Parameters> = workers.iter().map(|worker| Parameters::take(&worker.engine)).collect();
+    let scale = 1.0 / parameters.len() as f32;
+    let mut merged = parameters[0].clone();
+    match algorithm {
+        Algorithm::Tabular => {
+            let tables: Vec<&Arc<QValues>> = parameters.iter().map(|p| &p.q_values).collect();
+            merged.q_values = mean_q_values(&tables);
+        },
+        Algorithm::Linear => {
+            let q_weights = Arc::make_mut(&mut merged.q_weights);
+            for (i, weights) in q_weights.weights.iter_mut().enumerate() {
+                weights.iter_mut().for_each(|w| *w *= scale);
+                for other in &parameters[1..] {
+                    add_scaled(weights, &other.q_weights.weights[i], scale);
+                }
+            }
+        },
+        Algorithm::Dqn => {
+            let q_network = Arc::make_mut(&mut merged.q_network);
+            for (i, layer) in q_network.layers.iter_mut().enumerate() {
+                layer.weights.iter_mut().chain(layer.biases.iter_mut()).for_each(|w| *w *= scale);
+                for other in &parameters[1..] {
+                    add_scaled(&mut layer.weights, &other.q_network.layers[i].weights, scale);
+                    add_scaled(&mut layer.biases, &other.q_network.layers[i].biases, scale);
+                }
+            }
+        },
+    };
+    drop(parameters);
+    for worker in workers.iter_mut() {
+        merged.clone().swap(&mut worker.engine);
     }
 
-    println!("Ran {} simulations.", i);
+    // The second table of Double Q-learning, so that the first one stays the mean of the workers too
+    let seconds: Vec<Arc<QValues>> = workers.iter().filter_map(|worker| match worker.learner {
+        Learner::Tabular(ref learner) => learner.second.clone(),
+        _ => None,
+    }).collect();
+    if !seconds.is_empty() {
+        let second = mean_q_values(&seconds.iter().collect::<Vec<_>>());
+        for worker in workers.iter_mut() {
+            if let Learner::Tabular(ref mut learner) = worker.learner {
+                learner.second = Some(second.clone());
+            }
+        }
+    }
+}
+
+/// Plays the points from `first` to `first + count`, shared out between the workers, then averages their parameters
+fn play_round(workers: &mut [Worker], first: u32, count: u32, training: &Training) {
+    let threads = workers.len();
+    if threads == 1 {
+        for episode in first..first + count {
+            workers[0].play(episode, training);
+        }
+        return;
+    }
+    std::thread::scope(|scope| {
+        for (w, worker) in workers.iter_mut().enumerate() {
+            // Each worker plays every `threads`-th point of the round
+            scope.spawn(move || {
+                for episode in (first + w as u32..first + count).step_by(threads) {
+                    worker.play(episode, training);
+                }
+            });
+        }
+    });
+    average(workers, training.options.algorithm);
+}
 
-    // Save Q-Values
-    println!("Saving Q-values...");
-    let encoded = bincode::serialize(&engine.q_values).expect("Could not encode Q-Values to binary");
+/// Next to the Unity project if there is one, in the current directory otherwise
+fn default_output_path(file_name: &str) -> PathBuf {
     let mut path = ::std::env::current_dir().unwrap();
-    path.push(::std::path::PathBuf::from("Unity"));
+    path.push(PathBuf::from("Unity"));
     if !path.exists() {
         path = ::std::env::current_dir().unwrap();
     }
-    path.push(::std::path::PathBuf::from("q_values.bin"));
-    std::fs::write(path.clone(), encoded).expect("Unable to write Q-values.");
+    path.push(PathBuf::from(file_name));
+    path
+}
+
+fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("Run with --help for the list of options.");
+            std::process::exit(1);
+        }
+    };
+    let quiet = options.progress == Progress::Quiet;
+    let start_time = std::time::Instant::now();
 
-    println!("Done!\r\nSaved Q-values to \"{}\".", path.display());
+    let (name, file_name) = match options.algorithm {
+        Algorithm::Tabular => ("Q-values", "q_values.bin"),
+        Algorithm::Linear => ("Q-weights", "q_weights.bin"),
+        Algorithm::Dqn => ("Q-network", "q_network.bin"),
+    };
+    let path = options.output.clone().unwrap_or_else(|| default_output_path(file_name));
+    let checkpoint_path = options.checkpoint.clone().unwrap_or_else(|| {
+        let mut checkpoint_path = path.clone().into_os_string();
+        checkpoint_path.push(".checkpoint");
+        PathBuf::from(checkpoint_path)
+    });
+    let checkpoint = if options.resume {
+        let checkpoint = Checkpoint::load(&checkpoint_path).unwrap_or_else(|e| {
+            eprintln!("Could not load the checkpoint \"{}\": {}", checkpoint_path.display(), e);
+            std::process::exit(1);
+        });
+        if let Err(e) = checkpoint.check_options(&options) {
+            eprintln!("The checkpoint \"{}\" {}", checkpoint_path.display(), e);
+            std::process::exit(1);
+        }
+        Some(checkpoint)
+    } else {
+        None
+    };
+
+    let discounting_rate = options.discount.unwrap_or(0.95);
+    let (learner_type, learning_rate) = match options.algorithm {
+        // See `TdConfig`
+        Algorithm::Tabular => (agent::AgentType::TabularQLearning, options.td.learning_rate),
+        // Shared between the active features of a state
+        Algorithm::Linear => (agent::AgentType::LinearQLearning, options.learning_rate.unwrap_or(0.1)),
+        // See `DqnConfig`
+        Algorithm::Dqn => (agent::AgentType::Dqn, 0.0),
+    };
+
+    let seed = match checkpoint {
+        Some(ref checkpoint) => checkpoint.seed,
+        None => options.seed.unwrap_or_else(|| rand::thread_rng().gen()),
+    };
+    let parameters = match checkpoint {
+        Some(ref checkpoint) => checkpoint.parameters.clone(),
+        None => {
+            let mut parameters = Parameters::default();
+            match options.algorithm {
+                // Sparse tables allocate their states as they are visited
+                Algorithm::Tabular if options.dense => {
+                    if !quiet {
+                        println!("Initializing table...");
+                    }
+                    let state_count = options.encoder.encoder().state_count(&game_engine::GameEngine::with_seed(seed));
+                    if state_count > q_table::MAX_DENSE_STATES {
+                        eprintln!("The {} states of the encoder are too many for --storage dense", state_count);
+                        std::process::exit(1);
+                    }
+                    parameters.q_values = Arc::new(QValues::dense(state_count));
+                },
+                Algorithm::Tabular => {},
+                Algorithm::Linear => parameters.q_weights = Arc::new(linear_q::QWeights::blank()),
+                Algorithm::Dqn => parameters.q_network = Arc::new(dqn::DqnTrainer::new(options.dqn, seed).1),
+            };
+            parameters
+        },
+    };
+    // Each worker has its own seed, derived from the one of the training
+    let mut workers: Vec<Worker> = (0..options.threads as u64)
+        .map(|w| Worker::new(seed.wrapping_add(w), &options, learner_type, &parameters))
+        .collect();
+    drop(parameters);
+
+    let mut pool: Vec<Parameters> = Vec::new();
+    let mut i = 0;
+    let mut elapsed_before = 0;
+    if let Some(checkpoint) = checkpoint {
+        for (worker, state) in workers.iter_mut().zip(checkpoint.workers) {
+            worker.restore(state);
+        }
+        pool = checkpoint.pool;
+        i = checkpoint.episodes;
+        elapsed_before = checkpoint.elapsed;
+        if !quiet {
+            println!("Resuming after {} simulations.", i);
+        }
+    }
+
+    let save_checkpoint = |workers: &[Worker], pool: &[Parameters], episodes: u32| {
+        let checkpoint = Checkpoint::new(&options, seed, episodes, elapsed_before + start_time.elapsed().as_secs(), workers, pool);
+        if let Err(e) = checkpoint.save(&checkpoint_path) {
+            eprintln!("Unable to write the checkpoint to \"{}\": {}", checkpoint_path.display(), e);
+        }
+    };
+
+    if !quiet {
+        println!("Starting simulations...");
+    }
+    let threads = options.threads as u32;
+    let mut last_percent = 0;
+    let mut last_checkpoint = std::time::Instant::now();
+    loop {
+        if let Some(episodes) = options.episodes {
+            if i >= episodes {
+                break;
+            }
+        }
+        if let Some(duration) = options.duration {
+            if elapsed_before + start_time.elapsed().as_secs() >= duration {
+                break;
+            }
+        }
+
+        // Points played before the parameters are averaged, one at a time on a single thread
+        let remaining = options.episodes.map_or(u32::MAX, |episodes| episodes - i);
+        let round = if threads == 1 { 1 } else { (SYNC_EPISODES * threads).min(remaining) };
+        let training = Training {
+            options: &options,
+            learner_type,
+            learning_rate,
+            discounting_rate,
+            pool: &pool,
+        };
+        play_round(&mut workers, i, round, &training);
+
+        let previous = i;
+        i += round;
+
+        if options.pool_size > 0 && i / POOL_PERIOD > previous / POOL_PERIOD {
+            pool.push(Parameters::take(&workers[0].engine));
+            if pool.len() > options.pool_size {
+                pool.remove(0);
+            }
+        }
+
+        if options.checkpoint_interval > 0 && last_checkpoint.elapsed().as_secs() >= options.checkpoint_interval {
+            save_checkpoint(&workers, &pool, i);
+            last_checkpoint = std::time::Instant::now();
+            if options.progress == Progress::Lines {
+                println!("Saved checkpoint to \"{}\"", checkpoint_path.display());
+            }
+        }
+
+        // Progress towards whichever limit is the closest
+        let secs = elapsed_before + start_time.elapsed().as_secs();
+        let (count, total, text_current, text_total) = match (options.episodes, options.duration) {
+            (Some(episodes), Some(duration)) if (secs as f64 / duration as f64) > (i as f64 / episodes as f64) =>
+                (secs as i32, duration as i32, seconds_to_string(secs), seconds_to_string(duration)),
+            (Some(episodes), _) => (i as i32, episodes as i32, format!("{}", i), format!("{}", episodes)),
+            (None, Some(duration)) => (secs as i32, duration as i32, seconds_to_string(secs), seconds_to_string(duration)),
+            (None, None) => (0, 1, String::new(), String::new()),
+        };
+        match options.progress {
+            Progress::Bar => progress_bar(count, total, &text_current, &text_total),
+            Progress::Lines => {
+                let percent = 100 * count / total.max(1);
+                if percent > last_percent {
+                    last_percent = percent;
+                    let mut line = format!("{}% ({} / {}), {} simulations, exploration rate {:.3}", percent, text_current, text_total, i, workers[0].engine.explo_rate);
+                    // Of the first worker since the last line, to compare the update rules
+                    if let Learner::Tabular(ref mut learner) = workers[0].learner {
+                        if let Some(error) = learner.take_mean_error() {
+                            line.push_str(&format!(", mean TD error {:.4}", error));
+                        }
+                    }
+                    println!("{}", line);
+                }
+            },
+            Progress::Quiet => {},
+        };
+    }
+
+    if !quiet {
+        println!("Ran {} simulations.", i);
+    }
+    // Before mirroring the table, so that the training can go on with more episodes
+    if options.checkpoint_interval > 0 {
+        save_checkpoint(&workers, &pool, i);
+    }
+
+    let engine = &mut workers[0].engine;
+    let encoded = match options.algorithm {
+        Algorithm::Tabular => {
+            if options.mirror {
+                // The right player reads its half of the table
+                for entry in Arc::make_mut(&mut engine.q_values).values_mut() {
+                    entry.1 = entry.0;
+                }
+            }
+            let training = TrainingInfo {
+                update:      options.td.algorithm,
+                n_steps:     options.td.n_steps,
+                trace_decay: options.td.trace_decay,
+                learning_rate,
+                discount:    discounting_rate,
+                explo_start: options.explo_start,
+                explo_end:   options.explo_end,
+                explo_decay: options.explo_decay,
+                opponent:    if options.self_play { learner_type } else { options.opponent.agent_type },
+                self_play:   options.self_play,
+                pool_size:   options.pool_size as u32,
+                mirror:      options.mirror,
+                seed,
+                threads,
+                episodes:    i,
+            };
+            let table = QTable {
+                header:   QTableHeader::new(engine, options.encoder, training),
+                q_values: engine.q_values.clone(),
+            };
+            let mut encoded = Vec::new();
+            table.write_to(&mut encoded).map(|_| encoded)
+        },
+        Algorithm::Linear => {
+            let mut encoded = Vec::new();
+            engine.q_weights.write_to(&mut encoded).map(|_| encoded)
+        },
+        Algorithm::Dqn => {
+            let mut encoded = Vec::new();
+            engine.q_network.write_to(&mut encoded).map(|_| encoded)
+        },
+    };
+    if !quiet {
+        println!("Saving {}...", name);
+    }
+    let encoded = encoded.unwrap_or_else(|e| {
+        eprintln!("Could not encode the {} to binary: {}", name, e);
+        std::process::exit(1);
+    });
+    // The Unity build must never find a half-written file
+    if let Err(e) = format::write_atomic(&path, &encoded) {
+        eprintln!("Unable to write the {} to \"{}\": {}", name, path.display(), e);
+        std::process::exit(1);
+    }
+    if !quiet {
+        println!("Done!\r\nSaved {} to \"{}\".", name, path.display());
+    }
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("45"), Ok(45));
+    assert_eq!(parse_duration("1:30"), Ok(90));
+    assert_eq!(parse_duration("2:00:05"), Ok(7205));
+    // Only the first part can exceed its unit
+    assert_eq!(parse_duration("90:00"), Ok(5400));
+    assert!(parse_duration("1:60").is_err());
+    assert!(parse_duration("a:b").is_err());
+    assert!(parse_duration("1:2:3:4").is_err());
+    assert!(parse_duration("").is_err());
+    // Too large to be counted in seconds
+    assert!(parse_duration("400000000000000000:00").is_err());
+    assert!(parse_duration("18446744073709551615:00:00").is_err());
+}
+
+#[test]
+fn test_parse_rate() {
+    let rate = |value: &str| parse_rate("--discount", Some(String::from(value)));
+    assert_eq!(rate("0.9"), Ok(0.9));
+    assert_eq!(rate("0"), Ok(0.0));
+    assert_eq!(rate("1"), Ok(1.0));
+    assert!(rate("1.5").is_err());
+    assert!(rate("-0.1").is_err());
+    assert!(rate("x").is_err());
+    assert!(rate("NaN").is_err());
+    assert!(parse_rate("--discount", None).is_err());
+
+    assert_eq!(parse_finite("--explo-decay", Some(String::from("0.01"))), Ok(0.01));
+    assert!(parse_finite("--explo-decay", Some(String::from("NaN"))).is_err());
+    assert!(parse_positive_finite("--learning-rate", Some(String::from("inf"))).is_err());
+    assert!(parse_positive_finite("--learning-rate", Some(String::from("0"))).is_err());
+}
+
+#[test]
+fn test_incompatible_options() {
+    let parse = |args: &[&str]| Options::parse(args.iter().map(|arg| arg.to_string())).err();
+    let opponent_error = Some(String::from("--opponent cannot be used with --self-play or --pool"));
+    assert_eq!(parse(&["--self-play", "--opponent", "random"]), opponent_error);
+    assert_eq!(parse(&["--opponent", "random", "--pool", "3"]), opponent_error);
+    assert_eq!(parse(&["--n-steps", "3", "--trace-decay", "0.5"]), Some(String::from("--n-steps cannot be used with --trace-decay")));
+    assert_eq!(parse(&["--self-play", "--n-steps", "3"]), None);
+    assert_eq!(parse(&["--opponent", "random", "--trace-decay", "0.5"]), None);
+
+    // Options of another algorithm would be ignored
+    assert_eq!(parse(&["--algorithm", "linear", "--mirror"]), Some(String::from("--mirror can only be used with --algorithm tabular")));
+    assert_eq!(parse(&["--update", "sarsa", "--algorithm", "dqn"]), Some(String::from("--update can only be used with --algorithm tabular")));
+    assert_eq!(parse(&["--hidden", "32"]), Some(String::from("--hidden can only be used with --algorithm dqn")));
+    assert_eq!(parse(&["--algorithm", "dqn", "--explo-decay", "0.01"]), Some(String::from("--explo-decay cannot be used with --algorithm dqn, see --explo-frames")));
+    assert_eq!(parse(&["--algorithm", "dqn", "--hidden", "32", "--explo-frames", "1000"]), None);
+    assert_eq!(parse(&["--algorithm", "linear", "--explo-decay", "0.01"]), None);
+}
+
+#[test]
+fn test_checkpoint_options() {
+    let parse = |args: &[&str]| Options::parse(args.iter().map(|arg| arg.to_string())).unwrap();
+    let options = parse(&["--seed", "4", "--self-play", "--mirror"]);
+    let worker = Worker::new(4, &options, agent::AgentType::TabularQLearning, &Parameters::default());
+    let checkpoint = Checkpoint::new(&options, 4, 10, 1, &[worker], &[]);
+
+    assert_eq!(checkpoint.check_options(&options), Ok(()));
+    // The seed of the checkpoint is kept when none is given
+    assert_eq!(checkpoint.check_options(&parse(&["--self-play", "--mirror"])), Ok(()));
+    assert!(checkpoint.check_options(&parse(&["--seed", "5", "--self-play", "--mirror"])).is_err());
+    assert!(checkpoint.check_options(&parse(&["--self-play"])).is_err());
+    assert!(checkpoint.check_options(&parse(&["--pool", "3", "--mirror"])).is_err());
+    assert!(checkpoint.check_options(&parse(&["--opponent", "random", "--mirror"])).is_err());
+    assert!(checkpoint.check_options(&parse(&["--self-play", "--mirror", "--storage", "dense"])).is_err());
+    assert!(checkpoint.check_options(&parse(&["--self-play", "--mirror", "--threads", "2"])).is_err());
+}
+
+#[test]
+fn test_reproducible_training() {
+    fn train(args: &[&str], seed: u64) -> Vec<u8> {
+        let options = Options::parse(args.iter().map(|arg| arg.to_string())).unwrap();
+        let learner_type = match options.algorithm {
+            Algorithm::Tabular => agent::AgentType::TabularQLearning,
+            Algorithm::Linear => agent::AgentType::LinearQLearning,
+            Algorithm::Dqn => agent::AgentType::Dqn,
+        };
+        let mut parameters = Parameters::default();
+        match options.algorithm {
+            Algorithm::Tabular => {},
+            Algorithm::Linear => parameters.q_weights = Arc::new(linear_q::QWeights::blank()),
+            Algorithm::Dqn => parameters.q_network = Arc::new(dqn::DqnTrainer::new(options.dqn, seed).1),
+        };
+        let mut workers: Vec<Worker> = (0..options.threads as u64)
+            .map(|w| Worker::new(seed + w, &options, learner_type, &parameters))
+            .collect();
+        let training = Training {
+            options: &options,
+            learner_type,
+            learning_rate: 0.1,
+            discounting_rate: 0.95,
+            pool: &[],
+        };
+        for round in 0..2 {
+            play_round(&mut workers, round * 6, 6, &training);
+        }
+        let mut state = bincode::serialize(&Parameters::take(&workers[0].engine)).unwrap();
+        if let Learner::Tabular(ref learner) = workers[0].learner {
+            state.extend(bincode::serialize(&learner.second).unwrap());
+        }
+        state
+    }
+
+    // The parameters only depend on the seed and the number of threads, whatever the scheduling of the threads
+    for args in &[
+        &["--threads", "3"][..],
+        &["--threads", "3", "--update", "double-q", "--self-play"][..],
+        &["--threads", "2", "--algorithm", "linear"][..],
+        &["--threads", "2", "--algorithm", "dqn", "--batch-size", "4"][..],
+    ] {
+        assert_eq!(train(args, 3), train(args, 3), "training with {:?} is not reproducible", args);
+        assert_ne!(train(args, 3), train(args, 4));
+    }
 }
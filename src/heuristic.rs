@@ -0,0 +1,234 @@
+use agent::{ Agent, AgentType, Intent, throw_intents, return_home };
+use config::GameConfig;
+use frisbee::Frisbee;
+use game_engine::{ GameEngine, StateOfGame, throw_frisbee };
+use player::{ Player, PlayerSide };
+use vector2::Vector2;
+
+use rand::Rng;
+
+/// Frames the trajectory of the frisbee is predicted for
+const PREDICTION_FRAMES: usize = 240;
+/// Margin beyond which a throw is considered out of reach of the opponent, in court units
+const MARGIN_CAP: f64 = 2.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    /// Frames the bot waits before reacting when the frisbee changes hands
+    fn reaction_frames(self) -> u32 {
+        match self {
+            Difficulty::Easy => 20,
+            Difficulty::Medium => 10,
+            Difficulty::Hard => 3,
+        }
+    }
+
+    /// Random error added to the value of each throw, in court units
+    fn aim_noise(self) -> f64 {
+        match self {
+            Difficulty::Easy => 3.0,
+            Difficulty::Medium => 1.0,
+            Difficulty::Hard => 0.0,
+        }
+    }
+
+    /// Probability of dashing, each frame, when walking is too slow to reach the frisbee
+    fn dash_chance(self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.0,
+            Difficulty::Medium => 0.3,
+            Difficulty::Hard => 1.0,
+        }
+    }
+}
+
+/// Position of a thrown frisbee at the end of each of the next frames, and whether it can be caught there.
+/// Stops when the frisbee reaches a goal line or a lob lands.
+pub fn predict_path(frisbee: &Frisbee, players: &(Player, Player), config: &GameConfig) -> Vec<(Vector2, bool)> {
+    let mut frisbee = *frisbee;
+    let mut path = Vec::with_capacity(PREDICTION_FRAMES);
+    if frisbee.held_by_player.is_some() || frisbee.speed == 0.0 {
+        return path;
+    }
+    for _ in 0..PREDICTION_FRAMES {
+        // Same flight as in `GameEngine::step`, without catches
+        if frisbee.spin != 0.0 {
+            frisbee.direction = frisbee.direction.rotated(frisbee.spin);
+        }
+        ::collision::move_frisbee(&mut frisbee, players, config, false);
+        if frisbee.is_lob() {
+            frisbee.height += frisbee.vertical_speed;
+            frisbee.vertical_speed -= config.gravity;
+        }
+        path.push((frisbee.pos, !frisbee.is_airborne(config)));
+        if frisbee.pos.x.abs() >= config.goal_line || (frisbee.is_lob() && frisbee.height <= 0.0) {
+            break;
+        }
+    }
+    path
+}
+
+/// Cheap scripted opponent: it runs or dashes to where the frisbee can be intercepted,
+/// and throws where the opponent is the least likely to catch it, preferring the five-point band.
+///
+/// The difficulty sets how long it takes to react, how accurate the throws are and how often it dashes.
+pub struct HeuristicAgent {
+    pub difficulty: Difficulty,
+    holder:         Option<Option<PlayerSide>>, // Last seen holder of the frisbee, `None` before the first frame
+    delay:          u32,
+}
+
+impl HeuristicAgent {
+    pub fn new(difficulty: Difficulty) -> Self {
+        Self {
+            difficulty,
+            holder: None,
+            delay:  0,
+        }
+    }
+
+    /// Moves towards the first point of the trajectory that the player can reach in time
+    fn intercept(&self, side: PlayerSide, engine: &mut GameEngine) -> Intent {
+        let config = engine.config;
+        let player = match side {
+            PlayerSide::Left => engine.players.0,
+            PlayerSide::Right => engine.players.1,
+        };
+        let reach = config.player_radius + config.frisbee_radius;
+        let path = predict_path(&engine.frisbee, &engine.players, &config);
+
+        // Area the player can move in
+        let (min_x, max_x) = match side {
+            PlayerSide::Left => (-config.wall_ext, -config.net),
+            PlayerSide::Right => (config.net, config.wall_ext),
+        };
+        let clamp = |pos: Vector2| Vector2::new(
+            pos.x.max(min_x).min(max_x),
+            pos.y.max(-config.wall_vertical).min(config.wall_vertical),
+        );
+
+        let mut fallback = None;
+        for (i, &(pos, catchable)) in path.iter().enumerate() {
+            let target = clamp(pos);
+            if !catchable || (target - pos).length() > reach {
+                continue;
+            }
+            fallback = Some(target);
+            let frames = (i + 1) as f64;
+            // Aim for the frisbee to reach the middle of the player's reach
+            let distance = (target - player.pos).length() - reach / 2.0;
+            if distance <= 0.0 {
+                return Intent::None;
+            }
+            let dir = (target - player.pos).normalized();
+            if distance <= config.player_move_speed * frames {
+                return Intent::Move(dir);
+            }
+            // Dashes in a row are the fastest way to get there, as long as the first one does not overshoot
+            if distance <= config.player_dash_speed * frames && distance >= config.player_dash_power - reach &&
+               engine.agent_rng.gen_range(0.0, 1.0) < self.difficulty.dash_chance() {
+                return Intent::Dash(dir);
+            }
+        }
+
+        // Too late, get as close as possible to the last point
+        match fallback {
+            Some(target) if (target - player.pos).length() > config.player_move_speed => Intent::Move((target - player.pos).normalized()),
+            Some(_) => Intent::None,
+            None => return_home(engine, side),
+        }
+    }
+
+    /// Throw that ends the farthest from the opponent, scoring the most points
+    fn aim(&self, side: PlayerSide, engine: &mut GameEngine) -> Intent {
+        let config = engine.config;
+        let (player, opponent) = match side {
+            PlayerSide::Left => (engine.players.0, engine.players.1),
+            PlayerSide::Right => (engine.players.1, engine.players.0),
+        };
+        let reach = config.player_radius + config.frisbee_radius;
+        let noise = self.difficulty.aim_noise();
+
+        let mut best = (f64::MIN, Intent::None);
+        for intent in throw_intents() {
+            let mut frisbee = engine.frisbee;
+            throw_frisbee(&player, &mut frisbee, &intent, &config);
+            let path = predict_path(&frisbee, &engine.players, &config);
+            let end = match path.last() {
+                Some(&(pos, _)) => pos,
+                None => continue,
+            };
+
+            // Smallest distance the opponent would have left to cover, walking
+            let mut margin = MARGIN_CAP;
+            for (i, &(pos, catchable)) in path.iter().enumerate() {
+                if catchable && pos.x * opponent.get_horizontal_position() > 0.0 {
+                    let left = (pos - opponent.pos).length() - reach - config.player_move_speed * (i + 1) as f64;
+                    margin = margin.min(left);
+                }
+            }
+
+            let points = if end.x.abs() >= config.goal_line {
+                if end.y.abs() < config.five_points_band { config.five_points_goal_points } else { config.goal_points }
+            } else if end.x * opponent.get_horizontal_position() > 0.0 {
+                // A lob landing in the opponent's half
+                config.drop_points
+            } else {
+                -config.drop_points
+            };
+
+            let mut value = if margin < 0.0 { margin } else { margin + points as f64 };
+            if noise > 0.0 {
+                value += engine.agent_rng.gen_range(-noise, noise);
+            }
+            if value > best.0 {
+                best = (value, intent);
+            }
+        }
+        best.1
+    }
+}
+
+impl Agent for HeuristicAgent {
+    fn get_type(&self) -> AgentType {
+        match self.difficulty {
+            Difficulty::Easy => AgentType::HeuristicEasy,
+            Difficulty::Medium => AgentType::HeuristicMedium,
+            Difficulty::Hard => AgentType::HeuristicHard,
+        }
+    }
+
+    fn act(&mut self, side: PlayerSide, engine: &mut GameEngine) -> Intent {
+        if engine.state_of_game != StateOfGame::Playing {
+            self.holder = None;
+            return Intent::None;
+        }
+
+        let holder = engine.frisbee.held_by_player;
+        if self.holder != Some(holder) {
+            self.holder = Some(holder);
+            self.delay = self.difficulty.reaction_frames();
+        }
+        if self.delay > 0 {
+            self.delay -= 1;
+            return Intent::None;
+        }
+
+        let sliding = match side {
+            PlayerSide::Left => engine.players.0.slide.is_some(),
+            PlayerSide::Right => engine.players.1.slide.is_some(),
+        };
+        match holder {
+            Some(held_by) if held_by == side => self.aim(side, engine),
+            _ if sliding => Intent::None,
+            None if engine.frisbee.last_held != Some(side) => self.intercept(side, engine),
+            _ => return_home(engine, side),
+        }
+    }
+}
@@ -0,0 +1,381 @@
+extern crate rustjammers_engine;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+use rustjammers_engine::agent::{ HumanIntent, QVALUES_ACTIONS, action_from_index, greedy_action };
+use rustjammers_engine::config::GameConfig;
+use rustjammers_engine::game_engine::GameEngine;
+use rustjammers_engine::player::PlayerSide;
+use rustjammers_engine::q_table::{ QTable, QTableHeader };
+
+use serde::{ Serialize, Serializer };
+use serde::ser::SerializeMap;
+
+use std::fs::File;
+use std::io::{ BufWriter, Write };
+
+const USAGE: &str = "Usage: inspect_qvalues <q_values.bin> [options]
+
+Prints the coverage of a Q-table and the actions of its greedy policy.
+
+  --state <number> Prints the fields and the Q-values of one state
+  --csv <path>     Exports the updated states, one line per state and side
+  --json <path>    Exports the summary and the updated states
+  --config <path>  Game config the table was trained with, used to decode the states (default: the default config)
+  --help           Prints this message";
+
+/// Name of the action at `index`, on the court of the left player
+fn action_name(index: usize) -> String {
+    let intent = action_from_index(index, PlayerSide::Left);
+    let mut direction = Vec::new();
+    if intent.contains(HumanIntent::UP) {
+        direction.push("up");
+    }
+    if intent.contains(HumanIntent::DOWN) {
+        direction.push("down");
+    }
+    if intent.contains(HumanIntent::LEFT) {
+        direction.push("left");
+    }
+    if intent.contains(HumanIntent::RIGHT) {
+        direction.push("right");
+    }
+    let direction = if direction.is_empty() { String::from("idle") } else { direction.join("-") };
+    if intent.contains(HumanIntent::THROW) {
+        format!("throw {}", direction)
+    } else {
+        direction
+    }
+}
+
+/// Whether training ever moved a value away from zero
+fn is_updated(q_values: &[f32; QVALUES_ACTIONS]) -> bool {
+    q_values.iter().any(|&q| q != 0.0)
+}
+
+/// Q-values of one side in a state, the right one on its mirrored court
+#[derive(Serialize)]
+struct SideReport {
+    greedy:   String,
+    q_values: Vec<f32>,
+}
+
+impl SideReport {
+    fn new(q_values: &[f32; QVALUES_ACTIONS]) -> Option<Self> {
+        if !is_updated(q_values) {
+            return None;
+        }
+        Some(Self {
+            greedy:   action_name(greedy_action(q_values)),
+            q_values: q_values.to_vec(),
+        })
+    }
+}
+
+/// Fields of a state decoded by the encoder of the table, written as an object
+struct DecodedState(Vec<(&'static str, f64)>);
+
+impl Serialize for DecodedState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, value) in &self.0 {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+#[derive(Serialize)]
+struct StateReport {
+    hash:  u64,
+    state: DecodedState,
+    left:  Option<SideReport>,
+    right: Option<SideReport>,
+}
+
+#[derive(Serialize)]
+struct Coverage {
+    states: u64,
+    left:   usize, // Updated states
+    right:  usize,
+}
+
+/// Number of updated states in which each action is the greedy one
+#[derive(Serialize)]
+struct GreedyActions {
+    action: String,
+    left:   usize,
+    right:  usize,
+}
+
+#[derive(Serialize)]
+struct Report {
+    header:         QTableHeader,
+    matches_config: bool, // Whether the states can be decoded with the config
+    #[serde(skip)]
+    fields:         Vec<&'static str>, // Decoded from each state by the encoder of the table
+    coverage:       Coverage,
+    greedy_actions: Vec<GreedyActions>,
+    states:         Vec<StateReport>,
+}
+
+impl Report {
+    /// Report of the updated states of `table`, decoded with the config of `engine`
+    fn new(table: &QTable, engine: &GameEngine) -> Self {
+        let encoder = table.header.encoder.encoder();
+
+        // Sorted, so that exports are the same from one run to the next
+        let mut greedy = vec![(0, 0); QVALUES_ACTIONS];
+        let mut states = Vec::new();
+        for (hash, q_values) in table.q_values.touched() {
+            if is_updated(&q_values.0) {
+                greedy[greedy_action(&q_values.0)].0 += 1;
+            }
+            if is_updated(&q_values.1) {
+                greedy[greedy_action(&q_values.1)].1 += 1;
+            }
+            states.push(StateReport {
+                hash,
+                state: DecodedState(encoder.decode(engine, hash)),
+                left:  SideReport::new(&q_values.0),
+                right: SideReport::new(&q_values.1),
+            });
+        }
+
+        Self {
+            header:         table.header,
+            matches_config: table.header.check(engine).is_ok(),
+            fields:         encoder.decode(engine, 0).iter().map(|&(name, _)| name).collect(),
+            coverage:       Coverage {
+                states: table.header.state_count,
+                left:   states.iter().filter(|s| s.left.is_some()).count(),
+                right:  states.iter().filter(|s| s.right.is_some()).count(),
+            },
+            greedy_actions: greedy.iter().enumerate().map(|(i, &(left, right))| GreedyActions { action: action_name(i), left, right }).collect(),
+            states,
+        }
+    }
+}
+
+fn percent(count: usize, total: u64) -> f64 {
+    100.0 * count as f64 / total.max(1) as f64
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+fn print_summary(report: &Report) {
+    let training = &report.header.training;
+    println!("Trained for {} points against {:?}, seed {}, {} thread(s)", training.episodes, training.opponent, training.seed, training.threads);
+    println!("Update: {:?}, {} step(s), trace decay {}", training.update, training.n_steps, training.trace_decay);
+    println!("Learning rate {}, discount {}, exploration {} to {} (decay {})",
+             training.learning_rate, training.discount, training.explo_start, training.explo_end, training.explo_decay);
+    println!("Self-play: {}, pool: {}, mirror: {}", yes_no(training.self_play), training.pool_size, yes_no(training.mirror));
+    println!("Encoder: {:?}, states: {}, actions: {}", report.header.encoder, report.header.state_count, report.header.actions);
+    if !report.matches_config {
+        println!("Warning: the table was trained with another state encoding or court size, the states are not decoded correctly");
+    }
+
+    let coverage = &report.coverage;
+    println!();
+    println!("Updated states:");
+    println!("  left:  {} / {} ({:.2}%)", coverage.left, coverage.states, percent(coverage.left, coverage.states));
+    println!("  right: {} / {} ({:.2}%)", coverage.right, coverage.states, percent(coverage.right, coverage.states));
+
+    println!();
+    println!("Greedy actions of the updated states:");
+    println!("  {:<20} {:>16} {:>16}", "action", "left", "right");
+    for actions in &report.greedy_actions {
+        println!("  {:<20} {:>8} ({:>5.1}%) {:>8} ({:>5.1}%)", actions.action,
+                 actions.left, percent(actions.left, coverage.left as u64),
+                 actions.right, percent(actions.right, coverage.right as u64));
+    }
+}
+
+fn print_state(report: &StateReport) {
+    println!("State {}:", report.hash);
+    for (name, value) in &report.state.0 {
+        println!("  {:<12} {}", format!("{}:", name), value);
+    }
+    for (name, side) in [("left", &report.left), ("right", &report.right)].iter() {
+        match side {
+            Some(side) => {
+                println!("  {} side, greedy action {}:", name, side.greedy);
+                for (i, q) in side.q_values.iter().enumerate() {
+                    println!("    {:<20} {}", action_name(i), q);
+                }
+            },
+            None => println!("  {} side: never updated", name),
+        }
+    }
+}
+
+fn write_csv<W: Write>(mut writer: W, report: &Report) -> std::io::Result<()> {
+    write!(writer, "hash,side")?;
+    for name in &report.fields {
+        write!(writer, ",{}", name)?;
+    }
+    write!(writer, ",greedy,max_q")?;
+    for i in 0..QVALUES_ACTIONS {
+        write!(writer, ",{}", action_name(i))?;
+    }
+    writeln!(writer)?;
+
+    for state in &report.states {
+        for (name, side) in [("left", &state.left), ("right", &state.right)].iter() {
+            let side = match side {
+                Some(side) => side,
+                None => continue,
+            };
+            let max_q = side.q_values.iter().cloned().fold(f32::MIN, f32::max);
+            write!(writer, "{},{}", state.hash, name)?;
+            for (_, value) in &state.state.0 {
+                write!(writer, ",{}", value)?;
+            }
+            write!(writer, ",{},{}", side.greedy, max_q)?;
+            for q in &side.q_values {
+                write!(writer, ",{}", q)?;
+            }
+            writeln!(writer)?;
+        }
+    }
+    writer.flush()
+}
+
+fn write_json(path: &str, report: &Report) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut writer, report)?;
+    writer.flush()
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    eprintln!("Run with --help for the list of options.");
+    std::process::exit(1);
+}
+
+/// Reports what a Q-table learned, see `USAGE`.
+fn main() {
+    let mut table_path = None;
+    let mut state = None;
+    let mut csv = None;
+    let mut json = None;
+    let mut config = GameConfig::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| fail(format!("Missing value for {}", arg)));
+        match arg.as_str() {
+            "--state" => {
+                let value = value();
+                state = Some(value.parse::<u64>().unwrap_or_else(|_| fail(format!("Invalid state \"{}\"", value))));
+            },
+            "--csv" => csv = Some(value()),
+            "--json" => json = Some(value()),
+            "--config" => {
+                let path = value();
+                config = GameConfig::load(&path).unwrap_or_else(|e| fail(format!("Could not load the config \"{}\": {}", path, e)));
+            },
+            "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            _ if table_path.is_none() && !arg.starts_with("--") => table_path = Some(arg),
+            _ => fail(format!("Unknown argument \"{}\"", arg)),
+        }
+    }
+    let table_path = table_path.unwrap_or_else(|| fail(String::from("Missing Q-table path")));
+    let table = QTable::load(&table_path).unwrap_or_else(|e| {
+        eprintln!("Could not load the Q-table \"{}\": {}", table_path, e);
+        std::process::exit(1);
+    });
+
+    let mut engine = GameEngine::with_seed(0);
+    engine.config = config;
+    let encoder = table.header.encoder.encoder();
+    let report = Report::new(&table, &engine);
+
+    match state {
+        Some(hash) if hash >= report.header.state_count => {
+            eprintln!("State {} is not in the table, which has {} states", hash, report.header.state_count);
+            std::process::exit(1);
+        },
+        Some(hash) => match report.states.iter().find(|s| s.hash == hash) {
+            Some(state) => print_state(state),
+            None => print_state(&StateReport { hash, state: DecodedState(encoder.decode(&engine, hash)), left: None, right: None }),
+        },
+        None => print_summary(&report),
+    };
+
+    if let Some(path) = csv {
+        if let Err(e) = File::create(&path).and_then(|file| write_csv(BufWriter::new(file), &report)) {
+            eprintln!("Unable to write \"{}\": {}", path, e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = json {
+        if let Err(e) = write_json(&path, &report) {
+            eprintln!("Unable to write \"{}\": {}", path, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_table(q_values: rustjammers_engine::q_table::QValues) -> QTable {
+    let training = rustjammers_engine::q_table::TrainingInfo { seed: 1, episodes: 10, ..Default::default() };
+    QTable {
+        header:   QTableHeader::new(&GameEngine::with_seed(0), rustjammers_engine::state_encoder::EncoderType::Basic, training),
+        q_values: std::sync::Arc::new(q_values),
+    }
+}
+
+#[test]
+fn test_report() {
+    let engine = GameEngine::with_seed(0);
+    let mut q_values = rustjammers_engine::q_table::QValues::default();
+    let mut left = [0.0; QVALUES_ACTIONS];
+    left[2] = 1.0;
+    *q_values.entry(7) = (left, [0.0; QVALUES_ACTIONS]);
+    let mut right = [0.0; QVALUES_ACTIONS];
+    right[4] = -1.0;
+    *q_values.entry(3) = (left, right);
+    let report = Report::new(&test_table(q_values), &engine);
+
+    assert!(report.matches_config);
+    assert_eq!((report.coverage.left, report.coverage.right), (2, 1));
+    assert_eq!(report.greedy_actions[2].left, 2);
+    assert_eq!(report.greedy_actions.iter().map(|g| g.left + g.right).sum::<usize>(), 3);
+    // Sorted by state, and decoded with the encoder of the table
+    assert_eq!(report.states.iter().map(|s| s.hash).collect::<Vec<_>>(), vec![3, 7]);
+    assert_eq!(report.states[0].state.0, rustjammers_engine::state_encoder::EncoderType::Basic.encoder().decode(&engine, 3));
+    assert!(report.states[1].right.is_none());
+
+    let mut csv = Vec::new();
+    write_csv(&mut csv, &report).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 4); // The header, then one line per updated side
+    assert!(lines[1].starts_with("3,left,"));
+    assert!(lines[2].starts_with("3,right,"));
+    assert!(lines[3].starts_with("7,left,"));
+    assert!(lines.iter().all(|line| line.split(',').count() == lines[0].split(',').count()));
+}
+
+#[test]
+fn test_empty_report() {
+    let engine = GameEngine::with_seed(0);
+    let report = Report::new(&test_table(Default::default()), &engine);
+    assert_eq!((report.coverage.left, report.coverage.right), (0, 0));
+    assert!(report.states.is_empty());
+
+    // The columns are known even without any state
+    let mut csv = Vec::new();
+    write_csv(&mut csv, &report).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(csv.lines().count(), 1);
+    assert!(csv.starts_with("hash,side,player_x,player_y,frisbee_x,frisbee_y,own_throw,direction,greedy,max_q,"));
+}
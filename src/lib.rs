@@ -2,25 +2,42 @@ extern crate rand;
 #[macro_use]
 extern crate bitflags;
 extern crate bincode;
+extern crate serde;
+extern crate serde_json;
+#[macro_use]
+extern crate serde_derive;
 
 pub mod agent;
-mod player;
+pub mod player;
 mod frisbee;
 mod vector2;
 mod collision;
 mod shared_data;
 pub mod game_engine;
+pub mod replay;
+pub mod snapshot;
+pub mod format;
+pub mod config;
+pub mod mcts;
+pub mod dijkstra;
+pub mod minimax;
+pub mod heuristic;
+pub mod linear_q;
+pub mod dqn;
+pub mod q_table;
+pub mod state_encoder;
+pub mod td_learning;
 
 #[test]
 fn test_dijkstra() {
     let mut test = game_engine::GameEngine::new();
     let mut step: i64 = 0;
     test.reset();
-    test.send_type_p1(agent::AgentType::HumanPlayer as i8, 1000.0, 3);
-    test.send_type_p2(agent::AgentType::Dijkstra as i8, 1000.0, 3);
+    test.send_type_p1(agent::AgentType::HumanPlayer as i8, 1000.0, 3, 0.0);
+    test.send_type_p2(agent::AgentType::Dijkstra as i8, 1000.0, 3, 0.0);
     loop {
 
-        println!("STEP: {}", step.to_string());
+        println!("STEP: {}", step);
         test.epoch(agent::HumanIntent::IDLE, agent::HumanIntent::IDLE);
 
         if test.state_of_game == game_engine::StateOfGame::End {
@@ -31,3 +48,985 @@ fn test_dijkstra() {
 
     }
 }
+
+#[test]
+fn test_same_seed_same_match() {
+    use vector2::Vector2;
+
+    fn play(seed: u64) -> Vec<(Vector2, Vector2, Vector2, i8, i8)> {
+        let mut engine = game_engine::GameEngine::with_seed(seed);
+        engine.reset();
+        engine.send_type_p1(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+        engine.send_type_p2(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+
+        let mut frames = Vec::new();
+        while engine.state_of_game != game_engine::StateOfGame::End {
+            engine.epoch(agent::HumanIntent::IDLE, agent::HumanIntent::IDLE);
+            frames.push((
+                engine.players.0.pos,
+                engine.players.1.pos,
+                engine.frisbee.pos,
+                engine.players.0.score,
+                engine.players.1.score,
+            ));
+        }
+        frames
+    }
+
+    assert!(play(42) == play(42));
+    assert!(play(42) != play(43));
+}
+
+#[test]
+fn test_replay_playback() {
+    let path = std::env::temp_dir().join("rustjammers_test_replay.bin");
+
+    let mut engine = game_engine::GameEngine::with_seed(7);
+    engine.record_replays_to(path.clone());
+    engine.reset();
+    engine.send_type_p1(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+    engine.send_type_p2(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+    let mut recorded = Vec::new();
+    while engine.state_of_game != game_engine::StateOfGame::End {
+        engine.epoch(agent::HumanIntent::IDLE, agent::HumanIntent::IDLE);
+        recorded.push((engine.players.0.pos, engine.players.1.pos, engine.frisbee.pos));
+    }
+
+    let replay = replay::Replay::load(&path).unwrap();
+    assert_eq!(replay.frames.len(), recorded.len());
+
+    let mut engine = game_engine::GameEngine::new();
+    engine.start_playback(replay);
+    for frame in recorded.iter() {
+        engine.epoch(agent::HumanIntent::IDLE, agent::HumanIntent::IDLE);
+        assert!(*frame == (engine.players.0.pos, engine.players.1.pos, engine.frisbee.pos));
+    }
+    assert!(engine.state_of_game == game_engine::StateOfGame::End);
+
+    // Replays written with another format version are rejected
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[4] = bytes[4].wrapping_add(1);
+    match replay::Replay::read_from(&bytes[..]) {
+        Err(format::FileError::UnsupportedVersion(_)) => {},
+        _ => panic!("Replay with a wrong version was accepted"),
+    };
+
+    // Replays without agents cannot be played back, and are rejected instead of panicking in `start_playback`
+    let mut bytes = Vec::new();
+    replay::Replay::new(7, config::GameConfig::default()).write_to(&mut bytes).unwrap();
+    match replay::Replay::read_from(&bytes[..]) {
+        Err(format::FileError::Invalid(_)) => {},
+        _ => panic!("Replay without agents was accepted"),
+    };
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_snapshot_restore() {
+    let mut engine = game_engine::GameEngine::with_seed(3);
+    engine.reset();
+    engine.send_type_p1(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+    engine.send_type_p2(agent::AgentType::RandomRollout as i8, 10.0, 1, 0.0);
+    for _ in 0..200 {
+        engine.epoch(agent::HumanIntent::IDLE, agent::HumanIntent::IDLE);
+    }
+
+    let path = std::env::temp_dir().join("rustjammers_test_snapshot.bin");
+    engine.snapshot().save(&path).unwrap();
+    let snapshot = snapshot::Snapshot::load(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    let mut restored = game_engine::GameEngine::new();
+    restored.restore(&snapshot);
+    assert!(restored.agents.1.as_ref().unwrap().get_config() == engine.agents.1.as_ref().unwrap().get_config());
+
+    // Both engines play the exact same game from the snapshot on
+    for _ in 0..200 {
+        engine.epoch(agent::HumanIntent::IDLE, agent::HumanIntent::IDLE);
+        restored.epoch(agent::HumanIntent::IDLE, agent::HumanIntent::IDLE);
+        assert!(engine.players.0.pos == restored.players.0.pos);
+        assert!(engine.players.1.pos == restored.players.1.pos);
+        assert!(engine.frisbee.pos == restored.frisbee.pos);
+    }
+}
+
+#[test]
+fn test_game_config() {
+    let engine = game_engine::GameEngine::new();
+    assert_eq!(engine.hash_size(), 206910);
+
+    // Missing fields keep their default value
+    let config: config::GameConfig = serde_json::from_str("{ \"wall_vertical\": 6.0, \"max_round_points\": 12 }").unwrap();
+    assert_eq!(config.wall_vertical, 6.0);
+    assert_eq!(config.max_round_points, 12);
+    assert_eq!(config.wall_ext, config::GameConfig::default().wall_ext);
+    assert!(config.validate().is_ok());
+
+    let mut engine = game_engine::GameEngine::new();
+    assert!(engine.set_game_config(config));
+    assert_eq!(engine.hash_size(), 9 * 13 * 19 * 13 * 2 * 5);
+
+    // Rejected configs are logged, here out of the working directory
+    let log_path = std::env::temp_dir().join(format!("rustjammers_test_{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&log_path);
+    engine.log_path = Some(log_path.clone());
+    let mut invalid = config;
+    invalid.net = invalid.wall_ext + 1.0;
+    assert!(!engine.set_game_config(invalid));
+    assert!(engine.config == config);
+    assert!(std::fs::read_to_string(&log_path).unwrap().starts_with("Invalid game config"));
+    let _ = std::fs::remove_file(&log_path);
+}
+
+#[test]
+fn test_sets_and_tie_break() {
+    use agent::Intent;
+    use game_engine::StateOfGame;
+
+    let mut engine = game_engine::GameEngine::with_seed(5);
+    engine.reset();
+    engine.send_type_p1(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+    engine.send_type_p2(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+
+    // The next round of the set starts from the kickoff positions when the timer runs out, the points adding up
+    engine.state_of_game = StateOfGame::Playing;
+    engine.players.0.score = 3;
+    engine.players.0.pos.x = -2.0;
+    engine.time = engine.config.time_step / 2.0;
+    engine.step((Intent::None, Intent::None));
+    assert_eq!(engine.round_number, 2);
+    assert_eq!(engine.state_of_game, StateOfGame::Start);
+    assert_eq!(engine.time, engine.config.max_round_time);
+    assert_eq!(engine.players.0.pos.x, -engine.config.player_start_x);
+    assert_eq!((engine.players.0.score, engine.players.1.score), (3, 0));
+
+    // A tie when the last round runs out starts a sudden death
+    engine.players.1.score = 3;
+    engine.state_of_game = StateOfGame::Playing;
+    engine.time = engine.config.time_step / 2.0;
+    engine.step((Intent::None, Intent::None));
+    assert!(engine.tie_break);
+    assert_eq!(engine.round_number, 3);
+    assert_eq!(engine.state_of_game, StateOfGame::Playing);
+
+    // It goes on as long as nobody scores, so that a single player wins the set
+    engine.time = engine.config.time_step / 2.0;
+    engine.step((Intent::None, Intent::None));
+    assert!(engine.tie_break);
+    assert_eq!(engine.round_number, 4);
+    assert_eq!(engine.time, engine.config.tie_break_time);
+    assert_eq!((engine.players.0.sets, engine.players.1.sets), (0, 0));
+
+    // The next point wins the set
+    engine.players.0.score += 3;
+    engine.step((Intent::None, Intent::None));
+    assert_eq!(engine.state_of_game, StateOfGame::SetEnd);
+    assert_eq!((engine.players.0.sets, engine.players.1.sets), (1, 0));
+
+    // The next set starts after the break, from the initial positions
+    while engine.state_of_game == StateOfGame::SetEnd {
+        engine.step((Intent::None, Intent::None));
+    }
+    assert_eq!(engine.set_number, 2);
+    assert_eq!(engine.round_number, 1);
+    assert!(!engine.tie_break);
+    assert_eq!((engine.players.0.score, engine.players.1.score), (0, 0));
+    assert_eq!(engine.time, engine.config.max_round_time);
+
+    // Winning a second set ends the match
+    engine.players.1.sets = 1;
+    engine.players.0.score = engine.config.max_round_points;
+    engine.step((Intent::None, Intent::None));
+    assert_eq!(engine.state_of_game, StateOfGame::End);
+    assert_eq!((engine.players.0.sets, engine.players.1.sets), (2, 1));
+}
+
+#[test]
+fn test_lob_drop() {
+    use agent::Intent;
+    use frisbee::ThrowDirection;
+    use game_engine::StateOfGame;
+    use player::PlayerSide;
+    use vector2::Vector2;
+
+    let mut engine = game_engine::GameEngine::with_seed(3);
+    engine.reset();
+    engine.send_type_p1(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+    engine.send_type_p2(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+
+    engine.state_of_game = StateOfGame::Playing;
+    engine.players.0.pos = Vector2::new(-5.0, 0.0);
+    engine.players.1.pos = Vector2::new(3.0, 0.0);
+    engine.frisbee.held_by_player = Some(PlayerSide::Left);
+    engine.frisbee.pos = engine.players.0.pos;
+    engine.step((Intent::Lob(ThrowDirection::Middle), Intent::None));
+    assert!(engine.frisbee.is_lob());
+
+    // The lob flies over the opponent and lands behind them
+    let mut max_height: f64 = 0.0;
+    while engine.state_of_game == StateOfGame::Playing {
+        max_height = max_height.max(engine.frisbee.height);
+        assert_eq!(engine.frisbee.held_by_player, None);
+        engine.step((Intent::None, Intent::None));
+    }
+    assert!(max_height > engine.config.catch_height);
+    assert_eq!(engine.players.0.score, engine.config.drop_points);
+    assert_eq!(engine.players.1.score, 0);
+    assert_eq!(engine.frisbee.height, 0.0);
+}
+
+#[test]
+fn test_curve_shot() {
+    use agent::{ HumanIntent, Intent };
+    use frisbee::CurveDirection;
+    use game_engine::StateOfGame;
+    use player::PlayerSide;
+    use vector2::Vector2;
+
+    let mut engine = game_engine::GameEngine::with_seed(4);
+    engine.reset();
+    engine.send_type_p1(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+    engine.send_type_p2(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+
+    engine.state_of_game = StateOfGame::Playing;
+    engine.players.0.pos = Vector2::new(-5.0, 0.0);
+    engine.players.1.pos = Vector2::new(5.0, 4.0);
+    engine.frisbee.held_by_player = Some(PlayerSide::Left);
+    engine.frisbee.pos = engine.players.0.pos;
+
+    // Pulling back while holding up curves the shot upwards
+    let intent = agent::human_intent_to_intent(&engine, HumanIntent::THROW | HumanIntent::UP | HumanIntent::LEFT, PlayerSide::Left);
+    match intent {
+        Intent::Curve(CurveDirection::Up) => {},
+        _ => panic!("expected an upward curve, got {:?}", intent),
+    }
+
+    engine.step((intent, Intent::None));
+    assert!(engine.frisbee.direction.y < 0.0);
+    for _ in 0..60 {
+        engine.step((Intent::None, Intent::None));
+    }
+    assert!(engine.frisbee.direction.y > 0.0);
+    assert!((engine.frisbee.direction.length() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_swept_collisions() {
+    use vector2::Vector2;
+
+    let config = config::GameConfig::default();
+    let t = collision::sweep_circle(Vector2::new(-3.0, 0.0), Vector2::new(6.0, 0.0), Vector2::zero(), 1.0);
+    assert!((t.unwrap() - 1.0 / 3.0).abs() < 1e-12);
+    assert_eq!(collision::sweep_circle(Vector2::new(-3.0, 0.0), Vector2::new(1.0, 0.0), Vector2::zero(), 1.0), None);
+    assert_eq!(collision::sweep_circle(Vector2::new(-3.0, 2.0), Vector2::new(6.0, 0.0), Vector2::zero(), 1.0), None);
+    assert_eq!(collision::sweep_circle(Vector2::new(0.5, 0.0), Vector2::new(6.0, 0.0), Vector2::zero(), 1.0), Some(0.0));
+
+    let (t, wall) = collision::sweep_walls(Vector2::new(0.0, 0.0), Vector2::new(1.0, 2.0 * config.wall_vertical), &config).unwrap();
+    assert_eq!(wall, collision::Wall::Top);
+    assert!((t - 0.5).abs() < 1e-12);
+    // Past the goal lines there is no wall
+    assert_eq!(collision::sweep_walls(Vector2::new(config.goal_line, 0.0), Vector2::new(1.0, 2.0 * config.wall_vertical), &config), None);
+}
+
+#[test]
+fn test_fast_frisbee_caught() {
+    use agent::Intent;
+    use game_engine::StateOfGame;
+    use player::PlayerSide;
+    use vector2::Vector2;
+
+    for factor in 1..11 {
+        let mut engine = game_engine::GameEngine::with_seed(6);
+        engine.reset();
+        engine.send_type_p1(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+        engine.send_type_p2(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+
+        engine.state_of_game = StateOfGame::Playing;
+        engine.players.0.pos = Vector2::new(-8.0, 3.0);
+        engine.players.1.pos = Vector2::new(3.7, 0.0);
+        engine.players.0.slide = None;
+        engine.players.1.slide = None;
+        engine.frisbee.pos = Vector2::new(-5.0, 0.0);
+        engine.frisbee.direction = Vector2::new(1.0, 0.0);
+        engine.frisbee.speed = engine.config.initial_frisbee_speed * factor as f64;
+        engine.frisbee.last_held = Some(PlayerSide::Left);
+
+        for _ in 0..100 {
+            engine.step((Intent::None, Intent::None));
+            if engine.frisbee.held_by_player.is_some() || engine.state_of_game != StateOfGame::Playing {
+                break;
+            }
+        }
+        assert_eq!(engine.frisbee.held_by_player, Some(PlayerSide::Right), "frisbee not caught at {}x speed", factor);
+        assert_eq!(engine.players.0.score, 0);
+    }
+}
+
+#[test]
+fn test_fast_frisbee_rebounds() {
+    use player::{ Player, PlayerSide };
+    use vector2::Vector2;
+
+    let config = config::GameConfig::default();
+    let players = (Player::new(), Player::new());
+    let w = config.wall_vertical;
+
+    for factor in 1..11 {
+        let start = Vector2::new(-config.goal_line, 1.0);
+        let direction = Vector2::new(1.0, 3.0).normalized();
+        let mut frisbee = frisbee::Frisbee::new();
+        frisbee.pos = start;
+        frisbee.direction = direction;
+        frisbee.speed = config.initial_frisbee_speed * factor as f64;
+        frisbee.last_held = Some(PlayerSide::Left);
+        let step = frisbee.speed * config.frisbee_speed_scale;
+
+        let mut frame = 0;
+        while frisbee.pos.x < config.goal_line {
+            frame += 1;
+            collision::move_frisbee(&mut frisbee, &players, &config, false);
+            assert!(frisbee.pos.y.abs() <= w + 1e-9, "frisbee went through a wall at {}x speed", factor);
+
+            // Exact rebounds: the position is the straight path folded back between the walls
+            let travelled = direction * step * frame as f64;
+            if start.x + travelled.x < config.goal_line {
+                let period = 4.0 * w;
+                let unfolded = (start.y + travelled.y + w) % period;
+                let folded = if unfolded < 2.0 * w { unfolded - w } else { 3.0 * w - unfolded };
+                assert!((frisbee.pos.x - (start.x + travelled.x)).abs() < 1e-9);
+                assert!((frisbee.pos.y - folded).abs() < 1e-9, "wrong rebound at {}x speed", factor);
+            }
+        }
+        // Stopped exactly on the goal line, whatever the rounding of the last move
+        assert_eq!(frisbee.pos.x, config.goal_line);
+    }
+}
+
+#[test]
+fn test_rally_speed() {
+    use agent::Intent;
+    use frisbee::ThrowDirection;
+    use game_engine::StateOfGame;
+    use player::PlayerSide;
+    use vector2::Vector2;
+
+    let mut engine = game_engine::GameEngine::with_seed(8);
+    engine.reset();
+    engine.send_type_p1(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+    engine.send_type_p2(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+    let config = engine.config;
+
+    engine.state_of_game = StateOfGame::Playing;
+    engine.players.0.pos = Vector2::new(-5.0, 0.0);
+    engine.players.1.pos = Vector2::new(5.0, 0.0);
+    engine.players.0.slide = None;
+    engine.players.1.slide = None;
+    engine.frisbee.pos = engine.players.0.pos;
+    engine.frisbee.catch(PlayerSide::Left);
+    engine.frisbee.rally = 0;
+
+    // Each catch makes the next throw faster
+    let mut last_speed = 0.0;
+    for rally in 0..4 {
+        let intents = if rally % 2 == 0 {
+            (Intent::Throw(ThrowDirection::Middle), Intent::None)
+        } else {
+            (Intent::None, Intent::Throw(ThrowDirection::Middle))
+        };
+        engine.step(intents);
+        assert!(engine.frisbee.speed > last_speed);
+        last_speed = engine.frisbee.speed;
+        while engine.frisbee.held_by_player.is_none() {
+            engine.step((Intent::None, Intent::None));
+        }
+        assert_eq!(engine.frisbee.rally, rally + 1);
+    }
+
+    // Throwing right after a dash is a power throw, capped by the config
+    engine.frisbee.rally = 100;
+    engine.players.0.power_time = config.power_throw_window;
+    engine.step((Intent::Throw(ThrowDirection::Middle), Intent::None));
+    assert_eq!(engine.frisbee.speed, config.max_frisbee_speed);
+
+    // The rally ends with the point
+    engine.players.1.pos = Vector2::new(5.0, 4.0);
+    while engine.state_of_game == StateOfGame::Playing {
+        engine.step((Intent::None, Intent::None));
+    }
+    assert_eq!(engine.players.0.score, config.five_points_goal_points);
+    assert_eq!(engine.frisbee.rally, 0);
+
+    // Dashes cut short by a wall leave the same window for a power throw
+    engine.state_of_game = StateOfGame::Playing;
+    engine.players.0.pos = Vector2::new(-config.wall_ext + config.player_dash_speed / 2.0, 0.0);
+    engine.players.0.slide = None;
+    engine.players.0.power_time = 0.0;
+    engine.players.1.slide = None;
+    engine.frisbee.pos = engine.players.1.pos;
+    engine.frisbee.catch(PlayerSide::Right);
+    engine.step((Intent::Dash(Vector2::new(-1.0, 0.0)), Intent::None));
+    assert!(engine.players.0.slide.is_none());
+    assert_eq!(engine.players.0.pos.x, -config.wall_ext);
+    assert_eq!(engine.players.0.power_time, config.power_throw_window);
+
+    // And so do those cut short by a catch
+    engine.players.0.pos = Vector2::new(-5.0, 0.0);
+    engine.players.0.power_time = 0.0;
+    engine.frisbee.held_by_player = None;
+    engine.frisbee.last_held = Some(PlayerSide::Right);
+    engine.frisbee.speed = 0.0;
+    engine.frisbee.pos = Vector2::new(-5.0, 1.5);
+    engine.step((Intent::Dash(Vector2::new(0.0, 1.0)), Intent::None));
+    while engine.frisbee.held_by_player.is_none() {
+        assert!(engine.players.0.slide.is_some());
+        engine.step((Intent::None, Intent::None));
+    }
+    assert!(engine.players.0.slide.is_none());
+    assert!(engine.players.0.pos.y < config.player_dash_power);
+    assert_eq!(engine.players.0.power_time, config.power_throw_window);
+}
+
+#[test]
+fn test_max_hold_time() {
+    use agent::Intent;
+    use game_engine::StateOfGame;
+    use player::PlayerSide;
+    use vector2::Vector2;
+
+    let mut engine = game_engine::GameEngine::with_seed(10);
+    engine.reset();
+    engine.send_type_p1(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+    engine.send_type_p2(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+
+    engine.state_of_game = StateOfGame::Playing;
+    engine.players.0.pos = Vector2::new(-5.0, 0.0);
+    engine.players.0.slide = None;
+    engine.frisbee.catch(PlayerSide::Left);
+
+    let mut frames = 0;
+    let mut time_left = engine.get_state().hold_time_left;
+    assert_eq!(time_left, engine.config.max_hold_time);
+    while engine.frisbee.held_by_player.is_some() {
+        engine.step((Intent::None, Intent::None));
+        frames += 1;
+        if engine.frisbee.held_by_player.is_some() {
+            assert!(engine.get_state().hold_time_left < time_left);
+            time_left = engine.get_state().hold_time_left;
+        }
+    }
+    let expected = (engine.config.max_hold_time / engine.config.time_step).ceil() as i32;
+    assert!(frames >= expected && frames <= expected + 2, "thrown after {} frames instead of {}", frames, expected);
+    assert_eq!(engine.frisbee.last_held, Some(PlayerSide::Left));
+    assert_eq!(engine.frisbee.direction, Vector2::new(1.0, 0.0));
+    assert_eq!(engine.get_state().hold_time_left, 0.0);
+}
+
+#[test]
+fn test_mcts_agent() {
+    fn play(seed: u64) -> (i8, i8) {
+        let mut engine = game_engine::GameEngine::with_seed(seed);
+        engine.config.max_round_time = 3.0;
+        engine.config.best_of_sets = 1;
+        engine.send_type_p1(agent::AgentType::Mcts as i8, 20.0, 4, 0.0);
+        engine.send_type_p2(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+        engine.reset();
+        while engine.state_of_game != game_engine::StateOfGame::End {
+            engine.epoch(agent::HumanIntent::IDLE, agent::HumanIntent::IDLE);
+        }
+        (engine.players.0.score, engine.players.1.score)
+    }
+
+    // The search only draws from the engine's random streams
+    assert_eq!(play(11), play(11));
+
+    let agent = game_engine::GameEngine::create_agent(agent::AgentConfig { frames: 20.0, sim: 4, ..agent::AgentConfig::new(agent::AgentType::Mcts) });
+    assert_eq!(agent.get_type(), agent::AgentType::Mcts);
+    assert!(agent.get_type().is_search());
+}
+
+#[test]
+fn test_dijkstra_intercepts() {
+    use vector2::Vector2;
+    use player::PlayerSide;
+
+    let mut engine = game_engine::GameEngine::with_seed(3);
+    engine.send_type_p1(agent::AgentType::Dijkstra as i8, 0.0, 0, 0.0);
+    engine.send_type_p2(agent::AgentType::HumanPlayer as i8, 0.0, 0, 0.0);
+    engine.reset();
+    engine.state_of_game = game_engine::StateOfGame::Playing;
+    engine.players.0.pos = Vector2::new(-9.0, 0.0);
+    engine.players.0.slide = None;
+    engine.players.1.slide = None;
+
+    // Thrown by the right player, passing three units away from the left player
+    engine.frisbee.pos = Vector2::new(2.0, 0.0);
+    engine.frisbee.direction = (Vector2::new(-9.0, 3.0) - engine.frisbee.pos).normalized();
+    engine.frisbee.speed = engine.config.initial_frisbee_speed;
+    engine.frisbee.last_held = Some(PlayerSide::Right);
+
+    for _ in 0..120 {
+        engine.epoch(agent::HumanIntent::IDLE, agent::HumanIntent::IDLE);
+        if engine.frisbee.held_by_player.is_some() || engine.state_of_game != game_engine::StateOfGame::Playing {
+            break;
+        }
+    }
+    assert_eq!(engine.frisbee.held_by_player, Some(PlayerSide::Left));
+    assert_eq!(engine.players.1.score, 0);
+}
+
+#[test]
+fn test_minimax_agent() {
+    use vector2::Vector2;
+    use player::PlayerSide;
+
+    fn play(seed: u64) -> (i8, i8) {
+        let mut engine = game_engine::GameEngine::with_seed(seed);
+        engine.config.max_round_time = 3.0;
+        engine.config.best_of_sets = 1;
+        engine.send_type_p1(agent::AgentType::Minimax as i8, 0.0, 2, 0.0);
+        engine.send_type_p2(agent::AgentType::Random as i8, 0.0, 0, 0.0);
+        engine.reset();
+        while engine.state_of_game != game_engine::StateOfGame::End {
+            engine.epoch(agent::HumanIntent::IDLE, agent::HumanIntent::IDLE);
+        }
+        (engine.players.0.score, engine.players.1.score)
+    }
+
+    // Without a time budget, the search is fully deterministic
+    assert_eq!(play(5), play(5));
+
+    // A frisbee flying past the left player threatens its goal
+    let mut engine = game_engine::GameEngine::with_seed(0);
+    engine.reset();
+    engine.state_of_game = game_engine::StateOfGame::Playing;
+    engine.players.0.pos = Vector2::new(-9.0, 4.0);
+    engine.frisbee.pos = Vector2::new(0.0, -3.0);
+    engine.frisbee.direction = Vector2::new(-1.0, 0.0);
+    engine.frisbee.speed = engine.config.initial_frisbee_speed;
+    engine.frisbee.last_held = Some(PlayerSide::Right);
+    assert!(minimax::goal_threat(&engine, PlayerSide::Left) > 0.0);
+    assert_eq!(minimax::goal_threat(&engine, PlayerSide::Right), 0.0);
+    assert!(minimax::evaluate(&engine, PlayerSide::Left) < 0.0);
+
+    // Once in the way, the player is no longer threatened
+    engine.players.0.pos = Vector2::new(-9.0, -3.0);
+    assert_eq!(minimax::goal_threat(&engine, PlayerSide::Left), 0.0);
+
+    // The threat follows the curve of the frisbee and its rebound, as predicted for the engine
+    engine.frisbee.pos = Vector2::new(5.0, -3.0);
+    engine.frisbee.spin = engine.config.curve_spin;
+    let path = heuristic::predict_path(&engine.frisbee, &engine.players, &engine.config);
+    let crossing = path.iter().find(|&&(pos, _)| pos.x <= -9.0).unwrap().0;
+    assert!((crossing.y + 3.0).abs() > 1.0);
+    engine.players.0.pos = Vector2::new(-9.0, crossing.y);
+    assert_eq!(minimax::goal_threat(&engine, PlayerSide::Left), 0.0);
+
+    // The time budget is sent along with the agent
+    engine.send_type_p1(agent::AgentType::Minimax as i8, 0.0, 4, 0.005);
+    assert_eq!(engine.agents.0.as_ref().unwrap().get_config().time_budget, 0.005);
+
+    let agent = game_engine::GameEngine::create_agent(agent::AgentConfig { sim: 2, ..agent::AgentConfig::new(agent::AgentType::Minimax) });
+    assert_eq!(agent.get_type(), agent::AgentType::Minimax);
+    assert!(agent.get_type().is_search());
+}
+
+#[test]
+fn test_heuristic_agent() {
+    use vector2::Vector2;
+    use player::PlayerSide;
+
+    fn incoming(engine: &mut game_engine::GameEngine) {
+        engine.reset();
+        engine.state_of_game = game_engine::StateOfGame::Playing;
+        engine.players.0.slide = None;
+        engine.players.1.slide = None;
+        engine.players.0.pos = Vector2::new(-9.0, 0.0);
+        engine.frisbee.pos = Vector2::new(2.0, 0.0);
+        engine.frisbee.direction = Vector2::new(-1.0, 1.0).normalized();
+        engine.frisbee.speed = engine.config.initial_frisbee_speed;
+        engine.frisbee.spin = engine.config.curve_spin;
+        engine.frisbee.last_held = Some(PlayerSide::Right);
+    }
+
+    // The prediction follows the frisbee through its curve and its rebound on the top wall
+    let mut engine = game_engine::GameEngine::with_seed(0);
+    engine.send_type_p1(agent::AgentType::HumanPlayer as i8, 0.0, 0, 0.0);
+    engine.send_type_p2(agent::AgentType::HumanPlayer as i8, 0.0, 0, 0.0);
+    incoming(&mut engine);
+    engine.players.0.pos = Vector2::new(-9.0, -4.0);
+    let path = heuristic::predict_path(&engine.frisbee, &engine.players, &engine.config);
+    let mut rebounded = false;
+    for &(pos, catchable) in &path {
+        engine.epoch(agent::HumanIntent::IDLE, agent::HumanIntent::IDLE);
+        if engine.state_of_game != game_engine::StateOfGame::Playing {
+            break;
+        }
+        assert!((engine.frisbee.pos - pos).length() < 1e-9);
+        assert!(catchable);
+        rebounded |= engine.frisbee.direction.y < 0.0;
+    }
+    assert!(rebounded);
+    assert!(path.last().unwrap().0.x <= -engine.config.goal_line);
+
+    // Every level catches a frisbee it has time to reach
+    for &agent_type in &[agent::AgentType::HeuristicEasy, agent::AgentType::HeuristicMedium, agent::AgentType::HeuristicHard] {
+        let mut engine = game_engine::GameEngine::with_seed(1);
+        engine.send_type_p1(agent_type as i8, 0.0, 0, 0.0);
+        engine.send_type_p2(agent::AgentType::HumanPlayer as i8, 0.0, 0, 0.0);
+        incoming(&mut engine);
+        for _ in 0..120 {
+            engine.epoch(agent::HumanIntent::IDLE, agent::HumanIntent::IDLE);
+            if engine.frisbee.held_by_player.is_some() || engine.state_of_game != game_engine::StateOfGame::Playing {
+                break;
+            }
+        }
+        assert_eq!(engine.frisbee.held_by_player, Some(PlayerSide::Left), "{:?}", agent_type);
+        assert!(!agent_type.is_search());
+    }
+}
+
+#[test]
+fn test_linear_q_agent() {
+    use vector2::Vector2;
+    use player::PlayerSide;
+
+    let mut engine = game_engine::GameEngine::with_seed(0);
+    engine.send_type_p1(agent::AgentType::LinearQLearning as i8, 0.0, 0, 0.0);
+    engine.send_type_p2(agent::AgentType::LinearQLearning as i8, 0.0, 0, 0.0);
+    engine.reset();
+    engine.state_of_game = game_engine::StateOfGame::Playing;
+    engine.players.0.pos = Vector2::new(-5.0, 1.0);
+    engine.players.1.pos = Vector2::new(5.0, 1.0);
+    engine.frisbee.pos = Vector2::new(0.0, -2.0);
+    engine.frisbee.direction = Vector2::new(0.0, 1.0);
+    engine.frisbee.speed = engine.config.initial_frisbee_speed;
+
+    // Both sides see the same state on a symmetric court, and the features of a state are all different
+    let features = linear_q::features(&engine, PlayerSide::Left);
+    assert_eq!(features, linear_q::features(&engine, PlayerSide::Right));
+    for (i, &feature) in features.iter().enumerate() {
+        assert!(feature < linear_q::FEATURE_COUNT);
+        assert!(!features[..i].contains(&feature));
+    }
+    engine.players.1.pos = Vector2::new(5.0, -1.0);
+    assert_ne!(features, linear_q::features(&engine, PlayerSide::Left));
+
+    // Updates move the value of an action towards the target, and the greedy agent follows them
+    let mut q_weights = linear_q::QWeights::blank();
+    let right = agent::action_index(agent::HumanIntent::RIGHT, PlayerSide::Left);
+    let features = linear_q::features(&engine, PlayerSide::Left);
+    for _ in 0..10 {
+        let delta = 1.0 - q_weights.q_values(&features)[right];
+        q_weights.update(&features, right, delta, 0.5);
+    }
+    assert!((q_weights.q_values(&features)[right] - 1.0).abs() < 0.01);
+
+    // Saved weights keep their values, and weights of another shape are rejected
+    let mut bytes = Vec::new();
+    q_weights.write_to(&mut bytes).unwrap();
+    let loaded = linear_q::QWeights::read_from(&bytes[..]).unwrap();
+    assert_eq!(loaded.q_values(&features), q_weights.q_values(&features));
+    let mut bytes = Vec::new();
+    linear_q::QWeights { weights: vec![[0.0; agent::QVALUES_ACTIONS]; 3] }.write_to(&mut bytes).unwrap();
+    match linear_q::QWeights::read_from(&bytes[..]) {
+        Err(format::FileError::Invalid(_)) => {},
+        _ => panic!("Weights of another shape were accepted"),
+    };
+
+    engine.q_weights = std::sync::Arc::new(q_weights);
+    engine.explo_rate = 0.0;
+    let mut agent = game_engine::GameEngine::create_agent(agent::AgentConfig::new(agent::AgentType::LinearQLearning));
+    assert_eq!(agent.act(PlayerSide::Left, &mut engine), agent::Intent::Move(Vector2::new(1.0, 0.0)));
+    assert_eq!(engine.inputs.0, agent::HumanIntent::RIGHT);
+    assert!(agent::AgentType::LinearQLearning.is_q_learning());
+    assert!(!agent::AgentType::LinearQLearning.is_search());
+}
+
+#[test]
+fn test_dqn_agent() {
+    use vector2::Vector2;
+    use player::PlayerSide;
+
+    let mut engine = game_engine::GameEngine::with_seed(0);
+    engine.send_type_p1(agent::AgentType::Dqn as i8, 0.0, 0, 0.0);
+    engine.send_type_p2(agent::AgentType::Dqn as i8, 0.0, 0, 0.0);
+    engine.reset();
+    engine.players.0.pos = Vector2::new(-5.0, 1.0);
+    engine.players.1.pos = Vector2::new(5.0, 1.0);
+    engine.frisbee.pos = Vector2::new(0.0, -2.0);
+    let observation = dqn::observe(&engine, PlayerSide::Left);
+    assert_eq!(observation, dqn::observe(&engine, PlayerSide::Right));
+
+    // Transitions that end the point are learned, and the network survives serialization
+    let config = dqn::DqnConfig { batch_size: 8, train_period: 1, target_period: 10, learning_rate: 0.01, ..dqn::DqnConfig::default() };
+    let (mut trainer, mut network) = dqn::DqnTrainer::new(config, 0);
+    let right = agent::action_index(agent::HumanIntent::RIGHT, PlayerSide::Left);
+    for i in 0..1000 {
+        let action = i % agent::QVALUES_ACTIONS;
+        let reward = if action == right { 50.0 } else { -50.0 };
+        trainer.push_frame(&[dqn::Transition { observation, action, reward, next_observation: observation, done: true }], &mut network);
+    }
+    assert!((network.q_values(&observation)[right] - 0.5).abs() < 0.05);
+
+    // The exploration schedule counts frames, not the transitions of both sides
+    let mut self_play = dqn::DqnTrainer::with_network(dqn::DqnConfig { epsilon_frames: 100, ..config }, &network, rand::SeedableRng::seed_from_u64(0));
+    let transition = dqn::Transition { observation, action: right, reward: 0.0, next_observation: observation, done: false };
+    for _ in 0..50 {
+        self_play.push_frame(&[transition, transition], &mut network);
+    }
+    assert_eq!(self_play.frames, 50);
+    assert!((self_play.epsilon() - (config.epsilon_start + config.epsilon_end) / 2.0).abs() < 0.01);
+    let mut bytes = Vec::new();
+    network.write_to(&mut bytes).unwrap();
+    let network = dqn::QNetwork::read_from(&bytes[..]).unwrap();
+    let mut truncated = network.clone();
+    truncated.layers.pop();
+    let mut shrunk = network.clone();
+    shrunk.layers[0].weights.pop();
+    for invalid in &[truncated, shrunk] {
+        let mut bytes = Vec::new();
+        invalid.write_to(&mut bytes).unwrap();
+        match dqn::QNetwork::read_from(&bytes[..]) {
+            Err(format::FileError::Invalid(_)) => {},
+            _ => panic!("Network with wrong layers was accepted"),
+        };
+    }
+    assert!((trainer.epsilon() - 1.0).abs() < 0.01);
+
+    // The greedy agent plays the best action, mirrored for the right player
+    engine.q_network = std::sync::Arc::new(network);
+    engine.explo_rate = 0.0;
+    let mut agent = game_engine::GameEngine::create_agent(agent::AgentConfig::new(agent::AgentType::Dqn));
+    assert_eq!(agent.act(PlayerSide::Left, &mut engine), agent::Intent::Move(Vector2::new(1.0, 0.0)));
+    assert_eq!(agent.act(PlayerSide::Right, &mut engine), agent::Intent::Move(Vector2::new(-1.0, 0.0)));
+    assert_eq!(engine.inputs, (agent::HumanIntent::RIGHT, agent::HumanIntent::LEFT));
+    assert!(agent::AgentType::Dqn.is_q_learning());
+}
+
+#[test]
+fn test_tabular_q_mirror() {
+    use vector2::Vector2;
+    use player::PlayerSide;
+
+    let mut engine = game_engine::GameEngine::with_seed(0);
+    engine.send_type_p1(agent::AgentType::TabularQLearning as i8, 0.0, 0, 0.0);
+    engine.send_type_p2(agent::AgentType::TabularQLearning as i8, 0.0, 0, 0.0);
+    engine.reset();
+    engine.players.0.pos = Vector2::new(-6.0, 2.0);
+    engine.players.1.pos = Vector2::new(8.0, -1.0);
+    engine.frisbee.pos = Vector2::new(3.0, 1.0);
+    engine.frisbee.last_held = Some(PlayerSide::Right);
+    let right_view = engine.hash_for(PlayerSide::Right);
+    assert_eq!(engine.hash(), engine.hash_for(PlayerSide::Left));
+
+    // The right player sees the state of a left player in the mirrored position
+    let mut mirrored = game_engine::GameEngine::with_seed(0);
+    mirrored.players.0.pos = Vector2::new(-8.0, -1.0);
+    mirrored.frisbee.pos = Vector2::new(-3.0, 1.0);
+    mirrored.frisbee.last_held = Some(PlayerSide::Left);
+    assert_eq!(right_view, mirrored.hash());
+
+    // Each side plays its half of the table, the right one on the mirrored court
+    let mut q_values = q_table::QValues::default();
+    let right = agent::action_index(agent::HumanIntent::RIGHT, PlayerSide::Left);
+    q_values.entry(engine.hash()).0[right] = 1.0;
+    q_values.entry(right_view).1[right] = 1.0;
+    engine.q_values = std::sync::Arc::new(q_values);
+    engine.explo_rate = 0.0;
+    let mut agent = game_engine::GameEngine::create_agent(agent::AgentConfig::new(agent::AgentType::TabularQLearning));
+    assert_eq!(agent.act(PlayerSide::Left, &mut engine), agent::Intent::Move(Vector2::new(1.0, 0.0)));
+    assert_eq!(agent.act(PlayerSide::Right, &mut engine), agent::Intent::Move(Vector2::new(-1.0, 0.0)));
+    assert_eq!(engine.inputs, (agent::HumanIntent::RIGHT, agent::HumanIntent::LEFT));
+
+    // While training with --mirror, both sides play the left half, which is the only one updated
+    let mut q_values = q_table::QValues::default();
+    q_values.entry(engine.hash()).0[right] = 1.0;
+    q_values.entry(right_view).0[right] = 1.0;
+    q_values.entry(right_view).1[agent::action_index(agent::HumanIntent::UP, PlayerSide::Left)] = 1.0;
+    engine.q_values = std::sync::Arc::new(q_values);
+    engine.q_mirror = true;
+    assert_eq!(agent.act(PlayerSide::Left, &mut engine), agent::Intent::Move(Vector2::new(1.0, 0.0)));
+    assert_eq!(agent.act(PlayerSide::Right, &mut engine), agent::Intent::Move(Vector2::new(-1.0, 0.0)));
+}
+
+#[test]
+fn test_write_atomic() {
+    let path = std::env::temp_dir().join("rustjammers_test_atomic.bin");
+    format::write_atomic(&path, b"first").unwrap();
+    format::write_atomic(&path, b"second").unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"second");
+    // The temporary file was renamed over the target
+    assert!(!std::env::temp_dir().join("rustjammers_test_atomic.bin.tmp").exists());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_q_table_header() {
+    let engine = game_engine::GameEngine::with_seed(0);
+    let training = q_table::TrainingInfo { seed: 1, episodes: 10, ..q_table::TrainingInfo::default() };
+    let mut q_values = q_table::QValues::default();
+    *q_values.entry(3) = ([1.0; agent::QVALUES_ACTIONS], [2.0; agent::QVALUES_ACTIONS]);
+    let table = q_table::QTable {
+        header:   q_table::QTableHeader::new(&engine, state_encoder::EncoderType::Basic, training),
+        q_values: std::sync::Arc::new(q_values),
+    };
+    let mut bytes = Vec::new();
+    table.write_to(&mut bytes).unwrap();
+
+    let loaded = q_table::QTable::read_from(&bytes[..]).unwrap();
+    assert_eq!(loaded.header, table.header);
+    assert_eq!(loaded.q_values.get(3), table.q_values.get(3));
+    assert!(loaded.header.check(&engine).is_ok());
+
+    // A table of another court size does not describe the same states
+    let mut other = game_engine::GameEngine::with_seed(0);
+    other.config.wall_ext += 2.0;
+    assert!(loaded.header.check(&other).is_err());
+
+    // Nor does a table of another encoder
+    let mut header = table.header;
+    header.encoder = state_encoder::EncoderType::Full;
+    assert!(header.check(&engine).is_err());
+
+    // Tables with another number of actions are rejected before their values are read
+    let mut header = table.header;
+    header.actions += 1;
+    let mut bytes = Vec::new();
+    format::write_header(&mut bytes, &q_table::Q_TABLE_MAGIC, q_table::Q_TABLE_VERSION).unwrap();
+    bincode::serialize_into(&mut bytes, &header).unwrap();
+    assert!(q_table::QTable::read_from(&bytes[..]).is_err());
+
+    // Values that do not fit the states of the header are rejected before being allocated
+    let mut bytes = Vec::new();
+    let dense = q_table::QTable { header: table.header, q_values: std::sync::Arc::new(q_table::QValues::dense(10)) };
+    dense.write_to(&mut bytes).unwrap();
+    match q_table::QTable::read_from(&bytes[..]) {
+        Err(format::FileError::Invalid(_)) => {},
+        _ => panic!("Dense table of another size was accepted"),
+    };
+    let mut q_values = q_table::QValues::default();
+    *q_values.entry(table.header.state_count) = ([1.0; agent::QVALUES_ACTIONS], [0.0; agent::QVALUES_ACTIONS]);
+    let mut bytes = Vec::new();
+    q_table::QTable { header: table.header, q_values: std::sync::Arc::new(q_values) }.write_to(&mut bytes).unwrap();
+    match q_table::QTable::read_from(&bytes[..]) {
+        Err(format::FileError::Invalid(_)) => {},
+        _ => panic!("State out of the table was accepted"),
+    };
+    // The state count of the header cannot be checked without a config, but a huge one is not allocated
+    let mut header = table.header;
+    header.state_count = u64::MAX;
+    let mut bytes = Vec::new();
+    format::write_header(&mut bytes, &q_table::Q_TABLE_MAGIC, q_table::Q_TABLE_VERSION).unwrap();
+    bincode::serialize_into(&mut bytes, &header).unwrap();
+    bincode::serialize_into(&mut bytes, &(Some(u64::MAX), Vec::<(u64, q_table::StateValues)>::new())).unwrap();
+    match q_table::QTable::read_from(&bytes[..]) {
+        Err(format::FileError::Invalid(_)) => {},
+        _ => panic!("Huge dense table was accepted"),
+    };
+
+    // Bare tables written before the header was added
+    let bytes = bincode::serialize(&*table.q_values).unwrap();
+    match q_table::QTable::read_from(&bytes[..]) {
+        Err(format::FileError::InvalidHeader) => {},
+        _ => panic!("Table without a header was accepted"),
+    };
+}
+
+#[test]
+fn test_state_encoders() {
+    use vector2::Vector2;
+    use player::PlayerSide;
+    use state_encoder::EncoderType;
+
+    let mut engine = game_engine::GameEngine::with_seed(0);
+    engine.reset();
+    engine.players.0.pos = Vector2::new(-6.2, 2.4);
+    engine.frisbee.pos = Vector2::new(3.1, -1.8);
+    engine.frisbee.direction = Vector2::new(1.0, 0.0);
+    engine.frisbee.last_held = Some(PlayerSide::Left);
+
+    let basic = EncoderType::Basic.encoder();
+    assert_eq!(basic.encode(&engine, PlayerSide::Left), engine.hash());
+    assert_eq!(basic.state_count(&engine), engine.hash_size());
+    let fields = basic.decode(&engine, engine.hash());
+    assert_eq!(fields, vec![("player_x", -6.0), ("player_y", 2.0), ("frisbee_x", 3.0), ("frisbee_y", -2.0), ("own_throw", 1.0), ("direction", 2.0)]);
+
+    // Unlike the basic encoding, the full one sees the opponent
+    let full = EncoderType::Full.encoder();
+    let state = full.encode(&engine, PlayerSide::Left);
+    assert!(state < full.state_count(&engine));
+    engine.players.1.pos.y += 6.0;
+    assert_eq!(basic.encode(&engine, PlayerSide::Left), engine.hash());
+    assert!(full.encode(&engine, PlayerSide::Left) != state);
+    let fields = full.decode(&engine, full.encode(&engine, PlayerSide::Left));
+    assert_eq!(fields[0], ("player_x", -6.0));
+    assert_eq!(fields[6], ("holder", 3.0)); // Thrown by the player itself
+    assert!(basic.signature(&engine) != full.signature(&engine));
+}
+
+#[test]
+fn test_q_values_storage() {
+    let mut sparse = q_table::QValues::default();
+    let mut dense = q_table::QValues::dense(100);
+    assert!(sparse.is_empty() && !dense.is_empty());
+    for q_values in [&mut sparse, &mut dense].iter_mut() {
+        assert_eq!(q_values.get(42).map_or(0.0, |values| values.0[1]), 0.0);
+        q_values.entry(42).0[1] = 0.5;
+        q_values.entry(7).1[3] = -1.0;
+        // Allocated but never changed
+        q_values.entry(9);
+    }
+    assert_eq!(sparse.get(42), dense.get(42));
+    assert!(sparse.get(8).is_none() && dense.get(8).is_some());
+
+    // Only the states with values are saved, in either storage
+    let touched: Vec<u64> = sparse.touched().iter().map(|(state, _)| *state).collect();
+    assert_eq!(touched, vec![7, 42]);
+    let bytes = bincode::serialize(&dense).unwrap();
+    assert!(bytes.len() < 4 * agent::QVALUES_ACTIONS * 10);
+    let loaded: q_table::QValues = bincode::deserialize(&bytes).unwrap();
+    assert!(loaded.is_dense());
+    assert_eq!(loaded, dense);
+    let loaded: q_table::QValues = bincode::deserialize(&bincode::serialize(&sparse).unwrap()).unwrap();
+    assert_eq!(loaded.touched(), sparse.touched());
+    assert!(loaded.get(9).is_none());
+}
+
+#[test]
+fn test_td_learning() {
+    use player::PlayerSide;
+    use td_learning::{ TdAlgorithm, TdConfig, TdLearner };
+
+    let mut engine = game_engine::GameEngine::with_seed(0);
+    engine.reset();
+    let config = TdConfig { learning_rate: 1.0, discount: 0.5, n_steps: 2, ..TdConfig::default() };
+
+    // Two-step returns, nothing being expected after the goal
+    let mut learner = TdLearner::new(config, &engine.q_values);
+    engine.q_scored = false;
+    learner.observe(&mut engine, PlayerSide::Left, false, 10, 1, 1.0);
+    assert!(engine.q_values.get(10).is_none());
+    engine.q_scored = true;
+    learner.observe(&mut engine, PlayerSide::Left, false, 11, 2, 4.0);
+    assert_eq!(engine.q_values.get(10).unwrap().0[1], 3.0);
+    assert_eq!(engine.q_values.get(11).unwrap().0[2], 4.0);
+
+    // The traces of SARSA carry the reward of the goal back to the earlier frames
+    engine.q_values = std::sync::Arc::new(q_table::QValues::default());
+    let mut learner = TdLearner::new(TdConfig { algorithm: TdAlgorithm::Sarsa, n_steps: 1, trace_decay: 1.0, ..config }, &engine.q_values);
+    engine.q_scored = false;
+    learner.observe(&mut engine, PlayerSide::Right, true, 10, 1, 0.0);
+    engine.q_scored = true;
+    learner.observe(&mut engine, PlayerSide::Right, true, 11, 2, 1.0);
+    assert_eq!(engine.q_values.get(10).unwrap().1[1], 0.5);
+    assert_eq!(engine.q_values.get(11).unwrap().1[2], 1.0);
+
+    // Double Q-learning updates one of its tables, the engine playing their mean
+    engine.q_values = std::sync::Arc::new(q_table::QValues::default());
+    let mut learner = TdLearner::new(TdConfig { algorithm: TdAlgorithm::DoubleQLearning, n_steps: 1, ..config }, &engine.q_values);
+    learner.observe(&mut engine, PlayerSide::Left, false, 10, 1, 2.0);
+    assert_eq!(engine.q_values.get(10).unwrap().0[1], 1.0);
+    let second = learner.second.as_ref().unwrap().get(10).map_or(0.0, |values| values.0[1]);
+    assert!(second == 0.0 || second == 2.0);
+    assert!(learner.take_mean_error().is_some());
+}
@@ -0,0 +1,212 @@
+use agent::{ Agent, AgentType, Intent, QVALUES_ACTIONS, action_from_index, human_intent_to_intent };
+use format::{ FileError, write_header, read_header };
+use game_engine::GameEngine;
+use player::PlayerSide;
+
+use rand::Rng;
+
+use std::fs::File;
+use std::io::{ Read, Write, BufReader };
+use std::path::Path;
+
+pub const Q_WEIGHTS_MAGIC: [u8; 4] = *b"RJQW";
+pub const Q_WEIGHTS_VERSION: u16    = 1;
+
+/// Grids laid over each pair of variables, each one offset by a fraction of a tile
+const TILINGS: usize = 4;
+/// Tiles along each variable. Grids get one more so that the offset ones still cover the whole range.
+const TILES: usize = 8;
+const GRID_SIZE: usize = (TILES + 1) * (TILES + 1);
+/// Pairs of variables tiled together, see `features`
+const GROUPS: usize = 6;
+/// Who holds the frisbee (the player, the opponent, nobody with the frisbee coming or going), times sliding or not.
+/// Each context has its own weights.
+const CONTEXTS: usize = 8;
+
+/// Number of weights of each action
+pub const FEATURE_COUNT: usize = CONTEXTS * GROUPS * TILINGS * GRID_SIZE;
+/// Features active in any state, one per grid of its context
+pub const ACTIVE_FEATURES: usize = GROUPS * TILINGS;
+
+pub type Features = [usize; ACTIVE_FEATURES];
+
+/// Linear Q-values: the value of an action is the sum of its weights over the active features.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct QWeights {
+    pub weights: Vec<[f32; QVALUES_ACTIONS]>, // Empty until trained or loaded
+}
+
+impl QWeights {
+    /// Weights of an untrained agent, all at zero
+    pub fn blank() -> Self {
+        Self {
+            weights: vec![[0.0; QVALUES_ACTIONS]; FEATURE_COUNT],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weights.is_empty()
+    }
+
+    /// Writes the weights after a header and their shape, so that weights of other features are rejected
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), FileError> {
+        write_header(&mut writer, &Q_WEIGHTS_MAGIC, Q_WEIGHTS_VERSION)?;
+        ::bincode::serialize_into(&mut writer, &(FEATURE_COUNT as u32, QVALUES_ACTIONS as u32))?;
+        ::bincode::serialize_into(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, FileError> {
+        read_header(&mut reader, &Q_WEIGHTS_MAGIC, Q_WEIGHTS_VERSION)?;
+        let (features, actions): (u32, u32) = ::bincode::deserialize_from(&mut reader)?;
+        if features != FEATURE_COUNT as u32 || actions != QVALUES_ACTIONS as u32 {
+            return Err(FileError::Invalid(format!("the weights are for {} features and {} actions instead of {} and {}",
+                                                  features, actions, FEATURE_COUNT, QVALUES_ACTIONS)));
+        }
+        let q_weights: Self = ::bincode::deserialize_from(reader)?;
+        // Features index the weights directly
+        if q_weights.weights.len() != FEATURE_COUNT {
+            return Err(FileError::Invalid(format!("the file has {} weights instead of {}", q_weights.weights.len(), FEATURE_COUNT)));
+        }
+        Ok(q_weights)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        let file = File::open(path)?;
+        Self::read_from(BufReader::new(file))
+    }
+
+    pub fn q_values(&self, features: &Features) -> [f32; QVALUES_ACTIONS] {
+        let mut q_values = [0.0; QVALUES_ACTIONS];
+        if self.is_empty() {
+            return q_values;
+        }
+        for &feature in features.iter() {
+            for (q, w) in q_values.iter_mut().zip(self.weights[feature].iter()) {
+                *q += *w;
+            }
+        }
+        q_values
+    }
+
+    /// Moves the value of `action` by `learning_rate * delta`, shared between the active features
+    pub fn update(&mut self, features: &Features, action: usize, delta: f32, learning_rate: f32) {
+        if self.is_empty() {
+            *self = Self::blank();
+        }
+        let step = learning_rate * delta / ACTIVE_FEATURES as f32;
+        for &feature in features.iter() {
+            self.weights[feature][action] += step;
+        }
+    }
+}
+
+/// Maps `val` from `[min, max]` to `[0, 1]`
+fn normalize(val: f64, min: f64, max: f64) -> f64 {
+    ((val - min) / (max - min)).clamp(0.0, 1.0)
+}
+
+/// Tiles containing `(u, v)`, both between 0 and 1, in each of the grids
+fn tiles(u: f64, v: f64) -> [usize; TILINGS] {
+    let mut tiles = [0; TILINGS];
+    for (tiling, tile) in tiles.iter_mut().enumerate() {
+        // Uneven offsets along both variables, so that the grids do not all line up on the diagonal
+        let offset_u = tiling as f64 / TILINGS as f64;
+        let offset_v = ((tiling * 3) % TILINGS) as f64 / TILINGS as f64;
+        let i = ((u * TILES as f64 + offset_u) as usize).min(TILES);
+        let j = ((v * TILES as f64 + offset_v) as usize).min(TILES);
+        *tile = tiling * GRID_SIZE + i * (TILES + 1) + j;
+    }
+    tiles
+}
+
+/// Active features of the state seen by `side`.
+///
+/// The court is mirrored for the right player so that both sides share the same weights,
+/// see `agent::action_index` for the actions.
+pub fn features(engine: &GameEngine, side: PlayerSide) -> Features {
+    let config = &engine.config;
+    let frisbee = &engine.frisbee;
+    let (own, other, mirror) = match side {
+        PlayerSide::Left => (&engine.players.0, &engine.players.1, 1.0),
+        PlayerSide::Right => (&engine.players.1, &engine.players.0, -1.0),
+    };
+
+    let opponent_side = match side {
+        PlayerSide::Left => PlayerSide::Right,
+        PlayerSide::Right => PlayerSide::Left,
+    };
+    let holder = match frisbee.held_by_player {
+        Some(held_by) if held_by == side => 0,
+        Some(_) => 1,
+        None if frisbee.last_held == Some(opponent_side) => 2,
+        None => 3,
+    };
+    let context = holder * 2 + own.slide.is_some() as usize;
+
+    let max_speed = config.max_frisbee_speed;
+    let velocity = frisbee.direction * frisbee.speed;
+    let (wall_ext, goal_line, wall_vertical) = (config.wall_ext, config.goal_line, config.wall_vertical);
+    let grids = [
+        (normalize(frisbee.pos.x * mirror, -goal_line, goal_line), normalize(frisbee.pos.y, -wall_vertical, wall_vertical)),
+        (normalize(own.pos.x * mirror, -wall_ext, 0.0), normalize(own.pos.y, -wall_vertical, wall_vertical)),
+        (normalize(other.pos.x * mirror, 0.0, wall_ext), normalize(other.pos.y, -wall_vertical, wall_vertical)),
+        (
+            normalize((frisbee.pos.x - own.pos.x) * mirror, -goal_line, goal_line + wall_ext),
+            normalize(frisbee.pos.y - own.pos.y, -2.0 * wall_vertical, 2.0 * wall_vertical),
+        ),
+        (normalize(velocity.x * mirror, -max_speed, max_speed), normalize(velocity.y, -max_speed, max_speed)),
+        // Mirroring the court reverses the spin
+        (
+            normalize(frisbee.spin * mirror, -2.0 * config.curve_spin, 2.0 * config.curve_spin),
+            normalize(frisbee.height, 0.0, 2.0 * config.catch_height),
+        ),
+    ];
+
+    let mut features = [0; ACTIVE_FEATURES];
+    for (group, &(u, v)) in grids.iter().enumerate() {
+        let base = (context * GROUPS + group) * TILINGS * GRID_SIZE;
+        for (tiling, tile) in tiles(u, v).iter().enumerate() {
+            features[group * TILINGS + tiling] = base + tile;
+        }
+    }
+    features
+}
+
+/// Q-learning over tile-coded features of the whole state, see `features`.
+///
+/// Unlike `TabularQLearningAgent`, it sees the opponent, the speed of the frisbee and who holds it,
+/// and it can play either side. Its weights are in `GameEngine::q_weights`.
+pub struct LinearQLearningAgent {}
+
+impl Agent for LinearQLearningAgent {
+    fn get_type(&self) -> AgentType {
+        AgentType::LinearQLearning
+    }
+
+    fn act(&mut self, side: PlayerSide, engine: &mut GameEngine) -> Intent {
+        let index = if engine.agent_rng.gen_range(0.0, 1.0) < engine.explo_rate {
+            // Explore
+            engine.agent_rng.gen_range(0, QVALUES_ACTIONS)
+        } else {
+            // Exploit
+            let q_values = engine.q_weights.q_values(&features(engine, side));
+            let mut best = 0;
+            for (i, &q) in q_values.iter().enumerate() {
+                if q > q_values[best] {
+                    best = i;
+                }
+            }
+            best
+        };
+        let intent = action_from_index(index, side);
+
+        match side {
+            PlayerSide::Left => engine.inputs.0 = intent,
+            PlayerSide::Right => engine.inputs.1 = intent,
+        };
+
+        human_intent_to_intent(engine, intent, side)
+    }
+}
@@ -0,0 +1,345 @@
+use agent::{ Agent, AgentConfig, AgentType, Intent, HumanIntent, throw_intents, move_directions };
+use game_engine::{ GameEngine, StateOfGame };
+use player::PlayerSide;
+
+use std::time::{ Duration, Instant };
+
+/// Frames during which an action of the tree is played before the next decision
+const ACTION_FRAMES: u32 = 6;
+/// UCT exploration constant, rewards are between -1 and 1
+const EXPLORATION: f64 = 1.4;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Situation {
+    Holding,
+    Sliding,
+    Free,
+}
+
+pub fn situation(engine: &GameEngine, side: PlayerSide) -> Situation {
+    let player = match side {
+        PlayerSide::Left => &engine.players.0,
+        PlayerSide::Right => &engine.players.1,
+    };
+    if engine.frisbee.held_by_player == Some(side) {
+        Situation::Holding
+    } else if player.slide.is_some() {
+        Situation::Sliding
+    } else {
+        Situation::Free
+    }
+}
+
+/// Same actions as `RandomRolloutAgent`, plus waiting
+fn legal_actions(situation: Situation) -> Vec<Intent> {
+    let mut actions = vec![Intent::None];
+    match situation {
+        Situation::Holding => actions.extend(throw_intents()),
+        Situation::Free => {
+            let dirs = move_directions();
+            for dir in &dirs {
+                actions.push(Intent::Move(*dir));
+            }
+            for dir in &dirs {
+                actions.push(Intent::Dash(*dir));
+            }
+        },
+        // Nothing can be done until the end of the dash
+        Situation::Sliding => {},
+    }
+    actions
+}
+
+/// Intent sent on the `frame`-th frame of an action: moves are held, other actions only happen once
+fn intent_at(intent: Intent, frame: u32) -> Intent {
+    match intent {
+        Intent::Move(_) => intent,
+        _ if frame == 0 => intent,
+        _ => Intent::None,
+    }
+}
+
+/// Plays a frame in which `side` sends `intent` and the opponent its agent's intent
+fn play_frame(engine: &mut GameEngine, side: PlayerSide, intent: Intent) {
+    let intents = match side {
+        PlayerSide::Left => {
+            let mut opponent = engine.agents.1.take().unwrap();
+            let opponent_intent = opponent.act(PlayerSide::Right, engine);
+            engine.agents.1 = Some(opponent);
+            (intent, opponent_intent)
+        },
+        PlayerSide::Right => {
+            let mut opponent = engine.agents.0.take().unwrap();
+            let opponent_intent = opponent.act(PlayerSide::Left, engine);
+            engine.agents.0 = Some(opponent);
+            (opponent_intent, intent)
+        },
+    };
+    engine.step(intents);
+}
+
+fn is_over(engine: &GameEngine) -> bool {
+    engine.state_of_game == StateOfGame::End || engine.state_of_game == StateOfGame::SetEnd
+}
+
+fn scores(engine: &GameEngine, side: PlayerSide) -> (i8, i8) {
+    match side {
+        PlayerSide::Left => (engine.players.0.score, engine.players.1.score),
+        PlayerSide::Right => (engine.players.1.score, engine.players.0.score),
+    }
+}
+
+/// Node of an open-loop tree: it stands for a sequence of actions rather than a state,
+/// since the opponent and the rollouts are random.
+struct Node {
+    visits:    u32,
+    value:     f64, // Sum of the rewards
+    situation: Option<Situation>, // Unknown until the node is reached in a simulation
+    untried:   Vec<Intent>,
+    children:  Vec<(Intent, Node)>,
+}
+
+impl Node {
+    fn new() -> Self {
+        Self {
+            visits:    0,
+            value:     0.0,
+            situation: None,
+            untried:   Vec::new(),
+            children:  Vec::new(),
+        }
+    }
+
+    fn uct(&self, parent_visits: u32) -> f64 {
+        self.value / self.visits as f64 + EXPLORATION * ((parent_visits as f64).ln() / self.visits as f64).sqrt()
+    }
+}
+
+struct Search<'a> {
+    side:           PlayerSide,
+    rollout_frames: u32,
+    start_scores:   (i8, i8),
+    engine:         &'a mut GameEngine,
+}
+
+impl<'a> Search<'a> {
+    /// +1 if the player scored more than the opponent since the search started, -1 if less
+    fn reward(&self) -> f64 {
+        let (own, opponent) = scores(self.engine, self.side);
+        let gain = (own - self.start_scores.0) as i32 - (opponent - self.start_scores.1) as i32;
+        gain.signum() as f64
+    }
+
+    fn play_action(&mut self, intent: Intent) {
+        for frame in 0..ACTION_FRAMES {
+            if is_over(self.engine) {
+                return;
+            }
+            play_frame(self.engine, self.side, intent_at(intent, frame));
+        }
+    }
+
+    fn rollout(&mut self) -> f64 {
+        for _ in 0..self.rollout_frames {
+            if is_over(self.engine) {
+                break;
+            }
+            self.engine.epoch(HumanIntent::IDLE, HumanIntent::IDLE);
+        }
+        self.reward()
+    }
+
+    /// Selection, expansion, rollout and backpropagation from `node`
+    fn iterate(&mut self, node: &mut Node) -> f64 {
+        if is_over(self.engine) {
+            let reward = self.reward();
+            node.visits += 1;
+            node.value += reward;
+            return reward;
+        }
+
+        let current = situation(self.engine, self.side);
+        if node.situation != Some(current) {
+            // First visit, or the open-loop sequence led somewhere else this time
+            node.situation = Some(current);
+            node.untried = legal_actions(current);
+            node.children.clear();
+        }
+
+        let reward = if let Some(intent) = node.untried.pop() {
+            self.play_action(intent);
+            let reward = self.rollout();
+            let mut child = Node::new();
+            child.visits = 1;
+            child.value = reward;
+            node.children.push((intent, child));
+            reward
+        } else {
+            let parent_visits = node.visits.max(1);
+            let mut best = 0;
+            let mut best_uct = f64::MIN;
+            for (i, (_, child)) in node.children.iter().enumerate() {
+                let uct = child.uct(parent_visits);
+                if uct > best_uct {
+                    best_uct = uct;
+                    best = i;
+                }
+            }
+            let (intent, ref mut child) = node.children[best];
+            self.play_action(intent);
+            self.iterate(child)
+        };
+
+        node.visits += 1;
+        node.value += reward;
+        reward
+    }
+}
+
+/// Action being played, and the frames left before the next decision
+struct Pending {
+    intent:    Intent,
+    frame:     u32,
+    situation: Situation,
+}
+
+/// Monte Carlo Tree Search with UCT over the actions of `RandomRolloutAgent`.
+///
+/// Each action is played for a few frames. Iterations keep running while an action is played,
+/// on the subtree that follows it, which becomes the root of the next decision.
+/// Rollouts are random games of `frames` frames.
+pub struct MctsAgent {
+    pub frames:      f64, // Length of the rollouts
+    pub sim:         i8, // Iterations run each frame
+    pub time_budget: f64, // Search time each frame in seconds, replaces `sim` if positive
+    root:            Node,
+    pending:         Option<Pending>,
+}
+
+impl MctsAgent {
+    pub fn new(frames: f64, sim: i8, time_budget: f64) -> Self {
+        Self {
+            frames,
+            sim,
+            time_budget,
+            root:    Node::new(),
+            pending: None,
+        }
+    }
+
+    /// Runs the iterations allowed for this frame. The simulations first finish the pending action.
+    fn search(&mut self, side: PlayerSide, engine: &mut GameEngine) {
+        let mut sim_engine = GameEngine::with_seed(0);
+        let start = Instant::now();
+        let budget = Duration::from_millis((self.time_budget * 1000.0) as u64);
+        let mut iterations = 0;
+        loop {
+            if self.time_budget > 0.0 {
+                if start.elapsed() >= budget {
+                    break;
+                }
+            } else if iterations >= self.sim as i32 {
+                break;
+            }
+            iterations += 1;
+
+            engine.copy_in(&mut sim_engine);
+            // Each simulation gets its own random stream so that they explore different games
+            sim_engine.agent_rng = engine.fork_agent_rng();
+            let mut search = Search {
+                side,
+                rollout_frames: self.frames.max(0.0) as u32,
+                start_scores: scores(engine, side),
+                engine: &mut sim_engine,
+            };
+            if let Some(ref pending) = self.pending {
+                for frame in pending.frame..ACTION_FRAMES {
+                    play_frame(search.engine, side, intent_at(pending.intent, frame));
+                }
+            }
+            search.iterate(&mut self.root);
+        }
+    }
+}
+
+impl Agent for MctsAgent {
+    fn get_type(&self) -> AgentType {
+        AgentType::Mcts
+    }
+
+    fn get_config(&self) -> AgentConfig {
+        AgentConfig {
+            agent_type: AgentType::Mcts,
+            frames: self.frames,
+            sim: self.sim,
+            time_budget: self.time_budget,
+        }
+    }
+
+    fn act(&mut self, side: PlayerSide, engine: &mut GameEngine) -> Intent {
+        if engine.state_of_game != StateOfGame::Playing {
+            self.pending = None;
+            return Intent::None;
+        }
+
+        let current = situation(engine, side);
+        let interrupted = match self.pending {
+            // Catching the frisbee or the end of a dash calls for a new decision
+            Some(ref pending) => pending.situation != current,
+            None => false,
+        };
+        if interrupted {
+            self.pending = None;
+            self.root = Node::new();
+        }
+
+        if self.pending.is_none() {
+            if self.root.situation.is_some() && self.root.situation != Some(current) {
+                self.root = Node::new();
+            }
+            self.search(side, engine);
+
+            // Most visited action, whose subtree is kept for the next decision
+            let mut best: Option<usize> = None;
+            for (i, (_, child)) in self.root.children.iter().enumerate() {
+                match best {
+                    Some(b) if self.root.children[b].1.visits >= child.visits => {},
+                    _ => best = Some(i),
+                }
+            }
+            let intent = match best {
+                Some(i) => {
+                    let (intent, child) = self.root.children.swap_remove(i);
+                    self.root = child;
+                    intent
+                },
+                None => {
+                    self.root = Node::new();
+                    Intent::None
+                },
+            };
+            self.pending = Some(Pending {
+                intent,
+                frame: 0,
+                situation: current,
+            });
+        } else {
+            self.search(side, engine);
+        }
+
+        let mut done = false;
+        let intent = match self.pending {
+            Some(ref mut pending) => {
+                let intent = intent_at(pending.intent, pending.frame);
+                pending.frame += 1;
+                done = pending.frame >= ACTION_FRAMES;
+                intent
+            },
+            None => Intent::None,
+        };
+        if done {
+            self.pending = None;
+        }
+        intent
+    }
+}
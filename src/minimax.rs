@@ -0,0 +1,276 @@
+use agent::{ Agent, AgentConfig, AgentType, Intent, throw_intents };
+use game_engine::{ GameEngine, StateOfGame };
+use heuristic::predict_path;
+use mcts::{ Situation, situation };
+use player::PlayerSide;
+use snapshot::{ Snapshot, AgentModel };
+use vector2::Vector2;
+
+use std::time::{ Duration, Instant };
+
+/// Frames during which both players play the actions chosen at one depth of the tree
+const ACTION_FRAMES: u32 = 8;
+/// Deepest search, in actions of each player
+pub const MAX_DEPTH: i8 = 4;
+
+/// Value of a state for `side`, in points. Higher is better.
+pub type Evaluation = fn(&GameEngine, PlayerSide) -> f64;
+
+fn opponent(side: PlayerSide) -> PlayerSide {
+    match side {
+        PlayerSide::Left => PlayerSide::Right,
+        PlayerSide::Right => PlayerSide::Left,
+    }
+}
+
+/// Points the frisbee is about to score against `side`, weighted by how far the player
+/// is from where it will cross its line: 0 if the player can get there in time.
+pub fn goal_threat(engine: &GameEngine, side: PlayerSide) -> f64 {
+    let config = &engine.config;
+    let frisbee = &engine.frisbee;
+    if engine.state_of_game != StateOfGame::Playing || frisbee.held_by_player.is_some() ||
+       frisbee.last_held != Some(opponent(side)) {
+        return 0.0;
+    }
+    let player = match side {
+        PlayerSide::Left => &engine.players.0,
+        PlayerSide::Right => &engine.players.1,
+    };
+    let velocity = frisbee.direction * frisbee.speed * config.frisbee_speed_scale;
+    let towards_goal = -player.get_horizontal_aim_direction();
+    if velocity.x * towards_goal <= 0.0 {
+        return 0.0;
+    }
+
+    if (player.pos.x - frisbee.pos.x) * velocity.x < 0.0 {
+        // Already behind the player
+        return config.goal_points as f64;
+    }
+
+    // Flight of the engine, curving and bouncing off the walls, to the line of the player or to where a lob lands
+    let path = predict_path(frisbee, &engine.players, config);
+    let (frames, pos, points) = match path.iter().position(|&(pos, _)| (pos.x - player.pos.x) * velocity.x >= 0.0) {
+        Some(index) => {
+            let pos = path[index].0;
+            let points = if pos.y.abs() < config.five_points_band { config.five_points_goal_points } else { config.goal_points };
+            (index + 1, pos, points)
+        },
+        None if frisbee.is_lob() => (path.len(), path[path.len() - 1].0, config.drop_points),
+        None => return 0.0,
+    };
+    let reach = config.player_radius + config.frisbee_radius;
+    let gap = (pos - player.pos).length() - reach - config.player_move_speed * frames as f64;
+    points as f64 * (gap / config.wall_vertical).clamp(0.0, 1.0)
+}
+
+/// Score difference, corrected by the goals each player is about to concede
+pub fn evaluate(engine: &GameEngine, side: PlayerSide) -> f64 {
+    let (own, other) = match side {
+        PlayerSide::Left => (&engine.players.0, &engine.players.1),
+        PlayerSide::Right => (&engine.players.1, &engine.players.0),
+    };
+    (own.score - other.score) as f64 + goal_threat(engine, opponent(side)) - goal_threat(engine, side)
+}
+
+/// Coarse actions searched by the agent, for both players
+fn actions(situation: Situation) -> Vec<Intent> {
+    match situation {
+        Situation::Holding => throw_intents(),
+        Situation::Sliding => vec![Intent::None],
+        Situation::Free => {
+            let up = Vector2::new(0.0, 1.0);
+            let down = Vector2::new(0.0, -1.0);
+            vec![Intent::None, Intent::Move(up), Intent::Move(down), Intent::Dash(up), Intent::Dash(down)]
+        },
+    }
+}
+
+/// Intent sent on the `frame`-th frame of an action: moves are held, other actions only happen once
+fn intent_at(intent: Intent, frame: u32) -> Intent {
+    match intent {
+        Intent::Move(_) => intent,
+        _ if frame == 0 => intent,
+        _ => Intent::None,
+    }
+}
+
+/// The search ran out of time, its current iteration is dropped
+struct Timeout;
+
+struct Search<'a> {
+    side:       PlayerSide,
+    evaluate:   Evaluation,
+    deadline:   Option<Instant>,
+    sim_engine: &'a mut GameEngine,
+}
+
+impl<'a> Search<'a> {
+    /// Plays both actions from `snapshot`. The engine is left in the resulting state.
+    fn play(&mut self, snapshot: &Snapshot, own: Intent, other: Intent) -> Result<(), Timeout> {
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                return Err(Timeout);
+            }
+        }
+        snapshot.restore_with(self.sim_engine, (AgentModel::Rollout, AgentModel::Rollout));
+        for frame in 0..ACTION_FRAMES {
+            let intents = match self.side {
+                PlayerSide::Left => (intent_at(own, frame), intent_at(other, frame)),
+                PlayerSide::Right => (intent_at(other, frame), intent_at(own, frame)),
+            };
+            self.sim_engine.step(intents);
+            if self.sim_engine.state_of_game != StateOfGame::Playing {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Value of the best action in `snapshot` with alpha-beta pruning, along with that action.
+    /// The opponent answers each of our actions with its worst one for us.
+    fn max_value(&mut self, snapshot: &Snapshot, depth: i8, mut alpha: f64, beta: f64, first: Option<Intent>) -> Result<(f64, Intent), Timeout> {
+        snapshot.restore_with(self.sim_engine, (AgentModel::Rollout, AgentModel::Rollout));
+        let mut own_actions = actions(situation(self.sim_engine, self.side));
+        let other_actions = actions(situation(self.sim_engine, opponent(self.side)));
+        if let Some(first) = first {
+            // Best action of the previous iteration first, for more cutoffs
+            if let Some(i) = own_actions.iter().position(|a| *a == first) {
+                let first = own_actions.remove(i);
+                own_actions.insert(0, first);
+            }
+        }
+
+        let mut best = (f64::MIN, own_actions[0]);
+        for own in own_actions {
+            let mut value = f64::MAX;
+            for &other in &other_actions {
+                self.play(snapshot, own, other)?;
+                let child_value = if depth <= 1 || self.sim_engine.state_of_game != StateOfGame::Playing {
+                    (self.evaluate)(self.sim_engine, self.side)
+                } else {
+                    let child = Snapshot::take(self.sim_engine);
+                    self.max_value(&child, depth - 1, alpha, value.min(beta), None)?.0
+                };
+                value = value.min(child_value);
+                if value <= alpha {
+                    // We already have a better action
+                    break;
+                }
+            }
+            if value > best.0 {
+                best = (value, own);
+            }
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+        Ok(best)
+    }
+}
+
+/// Action being played, and the frames left before the next decision
+struct Pending {
+    intent:    Intent,
+    frame:     u32,
+    situation: Situation,
+}
+
+/// Adversarial search over alternating actions of both players, with alpha-beta pruning.
+///
+/// The depth is increased one action at a time until `sim` actions, or until `time_budget` runs out
+/// when it is positive. The leaves are scored by `evaluate`, which can be replaced.
+pub struct MinimaxAgent {
+    pub sim:         i8, // Depth of the search in actions of each player
+    pub time_budget: f64, // Search time each decision in seconds, replaces `sim` if positive
+    pub evaluate:    Evaluation,
+    pending:         Option<Pending>,
+    sim_engine:      GameEngine,
+}
+
+impl MinimaxAgent {
+    pub fn new(sim: i8, time_budget: f64) -> Self {
+        Self {
+            sim,
+            time_budget,
+            evaluate,
+            pending:    None,
+            sim_engine: GameEngine::with_seed(0),
+        }
+    }
+
+    fn search(&mut self, side: PlayerSide, engine: &GameEngine) -> Intent {
+        let (max_depth, deadline) = if self.time_budget > 0.0 {
+            (MAX_DEPTH, Some(Instant::now() + Duration::from_millis((self.time_budget * 1000.0) as u64)))
+        } else {
+            (self.sim.clamp(1, MAX_DEPTH), None)
+        };
+        let mut search = Search {
+            side,
+            evaluate: self.evaluate,
+            deadline,
+            sim_engine: &mut self.sim_engine,
+        };
+
+        let root = Snapshot::take(engine);
+        let mut best = None;
+        for depth in 1..=max_depth {
+            match search.max_value(&root, depth, f64::MIN, f64::MAX, best) {
+                Ok((_, intent)) => best = Some(intent),
+                Err(Timeout) => break,
+            }
+        }
+        best.unwrap_or(Intent::None)
+    }
+}
+
+impl Agent for MinimaxAgent {
+    fn get_type(&self) -> AgentType {
+        AgentType::Minimax
+    }
+
+    fn get_config(&self) -> AgentConfig {
+        AgentConfig {
+            sim: self.sim,
+            time_budget: self.time_budget,
+            ..AgentConfig::new(AgentType::Minimax)
+        }
+    }
+
+    fn act(&mut self, side: PlayerSide, engine: &mut GameEngine) -> Intent {
+        if engine.state_of_game != StateOfGame::Playing {
+            self.pending = None;
+            return Intent::None;
+        }
+
+        let current = situation(engine, side);
+        let interrupted = match self.pending {
+            // Catching the frisbee or the end of a dash calls for a new decision
+            Some(ref pending) => pending.situation != current,
+            None => false,
+        };
+        if interrupted || self.pending.is_none() {
+            let intent = self.search(side, engine);
+            self.pending = Some(Pending {
+                intent,
+                frame: 0,
+                situation: current,
+            });
+        }
+
+        let mut done = false;
+        let intent = match self.pending {
+            Some(ref mut pending) => {
+                let intent = intent_at(pending.intent, pending.frame);
+                pending.frame += 1;
+                done = pending.frame >= ACTION_FRAMES;
+                intent
+            },
+            None => Intent::None,
+        };
+        if done {
+            self.pending = None;
+        }
+        intent
+    }
+}
@@ -1,7 +1,8 @@
+use config::GameConfig;
 use vector2::Vector2;
-use frisbee::ThrowDirection;
+use frisbee::{ ThrowDirection, CurveDirection };
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum PlayerSide {
     Left = 0,
     Right = 1
@@ -22,7 +23,7 @@ pub fn player_side_from_i8(side: i8) -> Option<PlayerSide> {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Slide {
     pub target: Vector2,
     pub origin: Vector2,
@@ -38,12 +39,20 @@ impl Slide {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Player {
     pub pos:   Vector2,
     pub side:  Option<PlayerSide>,
     pub score: i8,
+    pub sets:  i8,
     pub slide: Option<Slide>,
+    pub power_time: f64, // Time left to make a power throw after a dash
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Player {
@@ -52,12 +61,14 @@ impl Player {
             pos:   Vector2::zero(),
             side:  None,
             score: 0,
+            sets:  0,
             slide: None,
+            power_time: 0.0,
         }
     }
 
     pub fn get_horizontal_position(&self) -> f64 {
-        self.get_horizontal_aim_direction() * -1.0
+        -self.get_horizontal_aim_direction()
     }
 
     pub fn get_horizontal_aim_direction(&self) -> f64 {
@@ -101,6 +112,27 @@ impl Player {
         }
     }
 
+    /// Initial direction and spin of a curve shot. The frisbee leaves towards the opposite
+    /// side and bends back towards `dir` on its way to the opponent.
+    pub fn get_curve(&self, dir: &CurveDirection, config: &GameConfig) -> (Vector2, f64) {
+        let horizontal = self.get_horizontal_aim_direction();
+        match dir {
+            CurveDirection::Up => (
+                self.get_throw_vector(&ThrowDirection::LightDown),
+                horizontal * config.curve_spin,
+            ),
+            CurveDirection::Down => (
+                self.get_throw_vector(&ThrowDirection::LightUp),
+                -horizontal * config.curve_spin,
+            ),
+        }
+    }
+
+    /// Throws made while dashing or right after are power throws
+    pub fn can_power_throw(&self) -> bool {
+        self.slide.is_some() || self.power_time > 0.0
+    }
+
     pub fn dash(&mut self, dir: Vector2) {
         if self.slide.is_none() {
             self.slide = Some(Slide {
@@ -111,6 +143,13 @@ impl Player {
         }
     }
 
+    /// Stops the dash, however it ended, leaving `power_throw_window` to make a power throw
+    pub fn end_slide(&mut self, config: &GameConfig) {
+        if self.slide.take().is_some() {
+            self.power_time = config.power_throw_window;
+        }
+    }
+
     pub fn dash_to_pos(&mut self, pos: Vector2) {
         self.slide = Some(Slide {
             origin: self.pos,
@@ -0,0 +1,286 @@
+use agent::{ AgentType, QVALUES_ACTIONS };
+use format::{ FileError, write_header, read_header };
+use game_engine::GameEngine;
+use state_encoder::EncoderType;
+use td_learning::TdAlgorithm;
+
+use serde::{ Serialize, Serializer, Deserialize, Deserializer };
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{ Read, Write, BufReader, BufWriter };
+use std::path::Path;
+use std::sync::Arc;
+
+pub const Q_TABLE_MAGIC: [u8; 4] = *b"RJQT";
+pub const Q_TABLE_VERSION: u16    = 4;
+/// Largest dense table, about 9 GB: the states of a header cannot be checked without the config it was trained with
+pub const MAX_DENSE_STATES: u64   = 1 << 26;
+
+pub type ActionValues = [f32; QVALUES_ACTIONS];
+/// Values of the left player in a state, then of the right player on its mirrored court
+pub type StateValues = (ActionValues, ActionValues);
+
+const BLANK: StateValues = ([0.0; QVALUES_ACTIONS], [0.0; QVALUES_ACTIONS]);
+
+/// Q-values of `TabularQLearningAgent`, for each state returned by its `StateEncoder`.
+///
+/// States that were never updated are worth zero, and only the others are saved.
+#[derive(Clone, Debug, PartialEq)]
+pub enum QValues {
+    /// States are allocated when first updated
+    Sparse(HashMap<u64, StateValues>),
+    /// Every state, indexed by its number: faster, but `StateEncoder::state_count` states are allocated up front
+    Dense(Vec<StateValues>),
+}
+
+impl Default for QValues {
+    fn default() -> Self {
+        QValues::Sparse(HashMap::new())
+    }
+}
+
+impl QValues {
+    /// Dense values of the states below `size`, see `StateEncoder::state_count`
+    pub fn dense(size: u64) -> Self {
+        QValues::Dense(vec![BLANK; size as usize])
+    }
+
+    /// Whether no state was ever allocated, as in an engine that did not load or train a table
+    pub fn is_empty(&self) -> bool {
+        match self {
+            QValues::Sparse(map) => map.is_empty(),
+            QValues::Dense(values) => values.is_empty(),
+        }
+    }
+
+    pub fn is_dense(&self) -> bool {
+        match self {
+            QValues::Sparse(_) => false,
+            QValues::Dense(_) => true,
+        }
+    }
+
+    /// Values of `state`, `None` if it was never allocated
+    pub fn get(&self, state: u64) -> Option<&StateValues> {
+        match self {
+            QValues::Sparse(map) => map.get(&state),
+            QValues::Dense(values) => values.get(state as usize),
+        }
+    }
+
+    /// Values of `state`, allocated at zero if needed
+    pub fn entry(&mut self, state: u64) -> &mut StateValues {
+        match self {
+            QValues::Sparse(map) => map.entry(state).or_insert(BLANK),
+            QValues::Dense(values) => &mut values[state as usize],
+        }
+    }
+
+    /// Allocated states, in no particular order
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (u64, &StateValues)> + '_> {
+        match self {
+            QValues::Sparse(map) => Box::new(map.iter().map(|(state, values)| (*state, values))),
+            QValues::Dense(values) => Box::new(values.iter().enumerate().map(|(state, values)| (state as u64, values))),
+        }
+    }
+
+    pub fn values_mut(&mut self) -> Box<dyn Iterator<Item = &mut StateValues> + '_> {
+        match self {
+            QValues::Sparse(map) => Box::new(map.values_mut()),
+            QValues::Dense(values) => Box::new(values.iter_mut()),
+        }
+    }
+
+    /// States with a value that is not zero, sorted
+    pub fn touched(&self) -> Vec<(u64, &StateValues)> {
+        let mut touched: Vec<(u64, &StateValues)> = self.iter().filter(|(_, values)| **values != BLANK).collect();
+        touched.sort_by_key(|(state, _)| *state);
+        touched
+    }
+}
+
+/// Saved form of `QValues`
+#[derive(Serialize, Deserialize)]
+struct StoredQValues<V> {
+    dense:  Option<u64>, // Number of states of a dense table
+    states: Vec<(u64, V)>,
+}
+
+impl StoredQValues<StateValues> {
+    /// Values of the stored states, which must all be below `state_count`, checked before anything is allocated
+    fn into_q_values(self, state_count: u64) -> Result<QValues, String> {
+        if let Some(size) = self.dense.filter(|&size| size != state_count) {
+            return Err(format!("the dense table has {} states instead of {}", size, state_count));
+        }
+        if let Some(size) = self.dense.filter(|&size| size > MAX_DENSE_STATES) {
+            return Err(format!("the dense table has {} states, more than the {} that can be allocated", size, MAX_DENSE_STATES));
+        }
+        if let Some((state, _)) = self.states.iter().find(|(state, _)| *state >= state_count) {
+            return Err(format!("state {} is out of the {} states of the table", state, state_count));
+        }
+        let mut q_values = match self.dense {
+            Some(size) => QValues::dense(size),
+            None => QValues::Sparse(HashMap::with_capacity(self.states.len())),
+        };
+        for (state, values) in self.states {
+            *q_values.entry(state) = values;
+        }
+        Ok(q_values)
+    }
+}
+
+impl Serialize for QValues {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let stored = StoredQValues {
+            dense:  match self {
+                QValues::Sparse(_) => None,
+                QValues::Dense(values) => Some(values.len() as u64),
+            },
+            states: self.touched(),
+        };
+        stored.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for QValues {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let stored: StoredQValues<StateValues> = StoredQValues::deserialize(deserializer)?;
+        // Without a header, only the states of a dense table are bounded
+        let state_count = stored.dense.unwrap_or(u64::MAX);
+        stored.into_q_values(state_count).map_err(::serde::de::Error::custom)
+    }
+}
+
+/// Settings of the training that produced a table, kept for reference
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrainingInfo {
+    pub update:        TdAlgorithm,
+    pub n_steps:       u32,
+    pub trace_decay:   f32,
+    pub learning_rate: f32,
+    pub discount:      f32,
+    pub explo_start:   f32,
+    pub explo_end:     f32,
+    pub explo_decay:   f32,
+    pub opponent:      AgentType, // The learner itself in self-play
+    pub self_play:     bool,
+    pub pool_size:     u32,
+    pub mirror:        bool,
+    pub seed:          u64,
+    pub threads:       u32,
+    pub episodes:      u32, // Points played
+}
+
+impl Default for TrainingInfo {
+    /// Default settings of `generate_qvalues`, before any point is played
+    fn default() -> Self {
+        Self {
+            update:        TdAlgorithm::QLearning,
+            n_steps:       1,
+            trace_decay:   0.0,
+            learning_rate: 0.8,
+            discount:      0.95,
+            explo_start:   1.0,
+            explo_end:     0.05,
+            explo_decay:   0.0025,
+            opponent:      AgentType::Random,
+            self_play:     false,
+            pool_size:     0,
+            mirror:        false,
+            seed:          0,
+            threads:       1,
+            episodes:      0,
+        }
+    }
+}
+
+/// Describes the states and actions of a table, written before it so that it can be checked before being read.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QTableHeader {
+    pub encoder:     EncoderType,
+    pub encoding:    u64, // See `StateEncoder::signature`
+    pub state_count: u64,
+    pub actions:     u32,
+    pub training:    TrainingInfo,
+}
+
+impl QTableHeader {
+    /// Header of a table of the states `encoder` gives with the config of `engine`
+    pub fn new(engine: &GameEngine, encoder: EncoderType, training: TrainingInfo) -> Self {
+        Self {
+            encoder,
+            encoding:    encoder.encoder().signature(engine),
+            state_count: encoder.encoder().state_count(engine),
+            actions:     QVALUES_ACTIONS as u32,
+            training,
+        }
+    }
+
+    /// Rejects tables whose states do not mean the same as in `engine`
+    pub fn check(&self, engine: &GameEngine) -> Result<(), FileError> {
+        let encoder = self.encoder.encoder();
+        if self.encoding != encoder.signature(engine) || self.state_count != encoder.state_count(engine) {
+            return Err(FileError::Invalid(String::from("the table was trained with another state encoding or court size")));
+        }
+        Ok(())
+    }
+}
+
+/// Q-values of `TabularQLearningAgent`, as saved to `q_values.bin`
+pub struct QTable {
+    pub header:   QTableHeader,
+    pub q_values: Arc<QValues>, // Shared with the engines playing it
+}
+
+impl QTable {
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), FileError> {
+        write_header(&mut writer, &Q_TABLE_MAGIC, Q_TABLE_VERSION)?;
+        ::bincode::serialize_into(&mut writer, &self.header)?;
+        ::bincode::serialize_into(&mut writer, &self.q_values)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn read_header<R: Read>(reader: &mut R) -> Result<QTableHeader, FileError> {
+        read_header(reader, &Q_TABLE_MAGIC, Q_TABLE_VERSION)?;
+        let header: QTableHeader = ::bincode::deserialize_from(&mut *reader)?;
+        // The size of the values depends on the number of actions
+        if header.actions != QVALUES_ACTIONS as u32 {
+            return Err(FileError::Invalid(format!("the table has {} actions instead of {}", header.actions, QVALUES_ACTIONS)));
+        }
+        Ok(header)
+    }
+
+    /// Reads the values that follow `header`, rejecting states it does not have
+    fn read_q_values<R: Read>(reader: R, header: &QTableHeader) -> Result<Arc<QValues>, FileError> {
+        let stored: StoredQValues<StateValues> = ::bincode::deserialize_from(reader)?;
+        let q_values = stored.into_q_values(header.state_count).map_err(FileError::Invalid)?;
+        Ok(Arc::new(q_values))
+    }
+
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, FileError> {
+        let header = Self::read_header(&mut reader)?;
+        let q_values = Self::read_q_values(reader, &header)?;
+        Ok(Self { header, q_values })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), FileError> {
+        let file = File::create(path)?;
+        self.write_to(BufWriter::new(file))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        let file = File::open(path)?;
+        Self::read_from(BufReader::new(file))
+    }
+
+    /// Loads a table and checks that it can be played by `engine`
+    pub fn load_for<P: AsRef<Path>>(path: P, engine: &GameEngine) -> Result<Self, FileError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let header = Self::read_header(&mut reader)?;
+        header.check(engine)?;
+        let q_values = Self::read_q_values(reader, &header)?;
+        Ok(Self { header, q_values })
+    }
+}
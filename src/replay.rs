@@ -0,0 +1,104 @@
+use agent::{ Intent, AgentType, HumanIntent };
+use config::GameConfig;
+use format::{ FileError, write_header, read_header };
+
+use std::fs::File;
+use std::io::{ Read, Write, BufReader, BufWriter };
+use std::path::{ Path, PathBuf };
+
+pub const REPLAY_MAGIC: [u8; 4] = *b"RJRP";
+pub const REPLAY_VERSION: u16    = 12;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    pub inputs:  (u8, u8), // `HumanIntent` bits
+    pub intents: (Intent, Intent),
+}
+
+impl ReplayFrame {
+    pub fn human_intents(&self) -> (HumanIntent, HumanIntent) {
+        (
+            HumanIntent::from_bits_truncate(self.inputs.0),
+            HumanIntent::from_bits_truncate(self.inputs.1),
+        )
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed:        u64,
+    pub config:      GameConfig,
+    pub agent_types: (AgentType, AgentType),
+    pub frames:      Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, config: GameConfig) -> Self {
+        Self {
+            seed,
+            config,
+            agent_types: (AgentType::None, AgentType::None),
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, inputs: (HumanIntent, HumanIntent), intents: (Intent, Intent)) {
+        self.frames.push(ReplayFrame {
+            inputs: (inputs.0.bits(), inputs.1.bits()),
+            intents,
+        });
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), FileError> {
+        write_header(&mut writer, &REPLAY_MAGIC, REPLAY_VERSION)?;
+        ::bincode::serialize_into(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Reads a replay, rejecting those that could not be played back
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, FileError> {
+        read_header(&mut reader, &REPLAY_MAGIC, REPLAY_VERSION)?;
+        let replay: Self = ::bincode::deserialize_from(reader)?;
+        // Both agents are created again to play the replay back
+        if replay.agent_types.0 == AgentType::None || replay.agent_types.1 == AgentType::None {
+            return Err(FileError::Invalid(String::from("the replay does not say which agents played")));
+        }
+        replay.config.validate().map_err(FileError::Invalid)?;
+        Ok(replay)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), FileError> {
+        let file = File::create(path)?;
+        self.write_to(BufWriter::new(file))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        let file = File::open(path)?;
+        Self::read_from(BufReader::new(file))
+    }
+}
+
+/// Records the match currently played by an engine, saved to `path` once the match ends.
+pub struct ReplayRecorder {
+    pub path:   PathBuf,
+    pub replay: Option<Replay>,
+}
+
+/// Re-drives an engine from a loaded replay, one frame per `epoch`.
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    pub frame:  usize,
+}
+
+impl ReplayPlayback {
+    pub fn next_frame(&mut self) -> Option<ReplayFrame> {
+        let frame = self.replay.frames.get(self.frame).cloned();
+        self.frame += 1;
+        frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.replay.frames.len()
+    }
+}
@@ -3,48 +3,70 @@ use game_engine::GameEngine;
 
 #[repr(C)]
 pub struct SharedData {
-    pub p1_x:          f64,
-    pub p1_y:          f64,
-    pub p1_score:      i8,
-    pub p1_side:       i8,
-
-    pub p2_x:          f64,
-    pub p2_y:          f64,
-    pub p2_score:      i8,
-    pub p2_side:       i8,
-
-    pub zbee_x:        f64,
-    pub zbee_y:        f64,
-    pub zbee_held:     i8,
-
-    pub time:          f64,
-    pub state_of_game: i8,
+    pub p1_x:           f64,
+    pub p1_y:           f64,
+    pub p1_score:       i8,
+    pub p1_side:        i8,
+
+    pub p2_x:           f64,
+    pub p2_y:           f64,
+    pub p2_score:       i8,
+    pub p2_side:        i8,
+
+    pub zbee_x:         f64,
+    pub zbee_y:         f64,
+    pub zbee_held:      i8,
+
+    pub time:           f64,
+    pub state_of_game:  i8,
+
+    pub p1_sets:        i8,
+    pub p2_sets:        i8,
+    pub set_number:     i8,
+    pub round_number:   i8,
+    pub tie_break:      i8,
+
+    pub zbee_height:    f64,
+    pub zbee_speed:     f64,
+    pub rally:          i8,
+    pub hold_time_left: f64, // Before the frisbee is thrown automatically, 0 if nobody holds it
 }
 
 impl SharedData {
     pub fn new() -> Self {
         Self {
-            p1_x:          0.0,
-            p1_y:          0.0,
-            p1_score:      0,
-            p1_side:       0,
+            p1_x:           0.0,
+            p1_y:           0.0,
+            p1_score:       0,
+            p1_side:        0,
+
+            p2_x:           0.0,
+            p2_y:           0.0,
+            p2_score:       0,
+            p2_side:        0,
 
-            p2_x:          0.0,
-            p2_y:          0.0,
-            p2_score:      0,
-            p2_side:       0,
+            zbee_x:         0.0,
+            zbee_y:         0.0,
+            zbee_held:      -1,
 
-            zbee_x:        0.0,
-            zbee_y:        0.0,
-            zbee_held:     -1,
+            time:           0.0,
 
-            time:          0.0,
+            state_of_game:  0,
 
-            state_of_game: 0,
+            p1_sets:        0,
+            p2_sets:        0,
+            set_number:     1,
+            round_number:   1,
+            tie_break:      0,
+
+            zbee_height:    0.0,
+            zbee_speed:     0.0,
+            rally:          0,
+            hold_time_left: 0.0,
         }
     }
 
-    pub fn to_game_engine(self, engine: &mut GameEngine) {
+    pub fn to_game_engine(&self, engine: &mut GameEngine) {
         engine.players.0.pos.x = self.p1_x;
         engine.players.0.pos.y = self.p1_y;
         engine.players.0.score = self.p1_score;
@@ -58,8 +80,17 @@ impl SharedData {
         engine.frisbee.pos.x = self.zbee_x;
         engine.frisbee.pos.y = self.zbee_y;
         engine.frisbee.held_by_player = ::player::player_side_from_i8(self.zbee_held);
+        engine.frisbee.height = self.zbee_height;
+        engine.frisbee.speed = self.zbee_speed;
+        engine.frisbee.rally = self.rally;
 
         engine.time = self.time;
         engine.state_of_game = ::game_engine::state_from_i8(self.state_of_game);
+
+        engine.players.0.sets = self.p1_sets;
+        engine.players.1.sets = self.p2_sets;
+        engine.set_number = self.set_number;
+        engine.round_number = self.round_number;
+        engine.tie_break = self.tie_break != 0;
     }
 }
@@ -0,0 +1,191 @@
+use agent::{ AgentConfig, AgentType, HumanIntent };
+use config::GameConfig;
+use frisbee::Frisbee;
+use format::{ FileError, write_header, read_header };
+use game_engine::{ GameEngine, StateOfGame, EngineRng };
+use player::Player;
+use linear_q::QWeights;
+use dqn::QNetwork;
+use q_table::QValues;
+use state_encoder::EncoderType;
+
+use std::fs::File;
+use std::io::{ Read, Write, BufReader, BufWriter };
+use std::path::Path;
+use std::sync::Arc;
+
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"RJSN";
+pub const SNAPSHOT_VERSION: u16    = 13;
+
+/// Which agent to install for a side when restoring a snapshot.
+#[derive(Clone, Copy, Debug)]
+pub enum AgentModel {
+    /// The agent that was playing when the snapshot was taken
+    Keep,
+    /// The agent that was playing, or a random agent if it cannot be simulated:
+    /// human players, and search agents which are too slow (see `AgentType::is_search`)
+    Rollout,
+    Replace(AgentConfig),
+}
+
+/// Full copy of the simulation state of a `GameEngine`.
+///
+/// The Q-values are shared with the engine the snapshot was taken from rather than copied,
+/// and they are not part of the saved file: a restored engine keeps its own table.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub players:       (Player, Player),
+    pub agents:        (AgentConfig, AgentConfig),
+    pub frisbee:       Frisbee,
+    pub time:          f64,
+    pub start_time:    f64,
+    pub state_of_game: StateOfGame,
+    pub set_number:    i8,
+    pub round_number:  i8,
+    pub tie_break:     bool,
+    pub config:        GameConfig,
+    pub seed:          u64,
+    pub rng:           EngineRng,
+    pub agent_rng:     EngineRng,
+
+    pub inputs:        (u8, u8), // `HumanIntent` bits
+    #[serde(skip)]
+    pub q_values:      Option<Arc<QValues>>,
+    #[serde(skip)]
+    pub q_encoder:     EncoderType, // Of `q_values`
+    #[serde(skip)]
+    pub q_mirror:      bool, // Of `q_values`
+    #[serde(skip)]
+    pub q_weights:     Option<Arc<QWeights>>,
+    #[serde(skip)]
+    pub q_network:     Option<Arc<QNetwork>>,
+    pub rewards:       (f32, f32),
+    pub q_scored:      bool,
+    pub explo_rate:    f32,
+}
+
+impl Snapshot {
+    pub fn take(engine: &GameEngine) -> Self {
+        fn agent_config(agent: &Option<Box<dyn (::agent::Agent)>>) -> AgentConfig {
+            match agent {
+                Some(agent) => agent.get_config(),
+                // The agent is currently acting, see `GameEngine::epoch`
+                None => AgentConfig::new(AgentType::None),
+            }
+        }
+
+        Self {
+            players: engine.players,
+            agents: (
+                agent_config(&engine.agents.0),
+                agent_config(&engine.agents.1),
+            ),
+            frisbee: engine.frisbee,
+            time: engine.time,
+            start_time: engine.start_time,
+            state_of_game: engine.state_of_game,
+            set_number: engine.set_number,
+            round_number: engine.round_number,
+            tie_break: engine.tie_break,
+            config: engine.config,
+            seed: engine.seed,
+            rng: engine.rng.clone(),
+            agent_rng: engine.agent_rng.clone(),
+
+            inputs: (engine.inputs.0.bits(), engine.inputs.1.bits()),
+            q_values: Some(engine.q_values.clone()),
+            q_encoder: engine.q_encoder,
+            q_mirror: engine.q_mirror,
+            q_weights: Some(engine.q_weights.clone()),
+            q_network: Some(engine.q_network.clone()),
+            rewards: engine.rewards,
+            q_scored: engine.q_scored,
+            explo_rate: engine.explo_rate,
+        }
+    }
+
+    pub fn restore(&self, engine: &mut GameEngine) {
+        self.restore_with(engine, (AgentModel::Keep, AgentModel::Keep));
+    }
+
+    pub fn restore_with(&self, engine: &mut GameEngine, models: (AgentModel, AgentModel)) {
+        fn create_agent(config: AgentConfig, model: AgentModel) -> Option<Box<dyn (::agent::Agent)>> {
+            let config = match model {
+                AgentModel::Keep => config,
+                AgentModel::Rollout => {
+                    if config.agent_type == AgentType::None ||
+                       config.agent_type == AgentType::HumanPlayer ||
+                       config.agent_type.is_search() {
+                        AgentConfig::new(AgentType::Random)
+                    } else {
+                        config
+                    }
+                },
+                AgentModel::Replace(config) => config,
+            };
+            if config.agent_type == AgentType::None {
+                return None;
+            }
+            Some(GameEngine::create_agent(config))
+        }
+
+        engine.players = self.players;
+        engine.agents = (
+            create_agent(self.agents.0, models.0),
+            create_agent(self.agents.1, models.1),
+        );
+        engine.frisbee = self.frisbee;
+        engine.time = self.time;
+        engine.start_time = self.start_time;
+        engine.state_of_game = self.state_of_game;
+        engine.set_number = self.set_number;
+        engine.round_number = self.round_number;
+        engine.tie_break = self.tie_break;
+        engine.config = self.config;
+        engine.seed = self.seed;
+        engine.rng = self.rng.clone();
+        engine.agent_rng = self.agent_rng.clone();
+        engine.playback = None;
+
+        engine.inputs = (
+            HumanIntent::from_bits_truncate(self.inputs.0),
+            HumanIntent::from_bits_truncate(self.inputs.1),
+        );
+        if let Some(ref q_values) = self.q_values {
+            engine.q_values = q_values.clone();
+            engine.q_encoder = self.q_encoder;
+            engine.q_mirror = self.q_mirror;
+        }
+        if let Some(ref q_weights) = self.q_weights {
+            engine.q_weights = q_weights.clone();
+        }
+        if let Some(ref q_network) = self.q_network {
+            engine.q_network = q_network.clone();
+        }
+        engine.rewards = self.rewards;
+        engine.q_scored = self.q_scored;
+        engine.explo_rate = self.explo_rate;
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), FileError> {
+        write_header(&mut writer, &SNAPSHOT_MAGIC, SNAPSHOT_VERSION)?;
+        ::bincode::serialize_into(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, FileError> {
+        read_header(&mut reader, &SNAPSHOT_MAGIC, SNAPSHOT_VERSION)?;
+        Ok(::bincode::deserialize_from(reader)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), FileError> {
+        let file = File::create(path)?;
+        self.write_to(BufWriter::new(file))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        let file = File::open(path)?;
+        Self::read_from(BufReader::new(file))
+    }
+}
@@ -0,0 +1,307 @@
+use game_engine::GameEngine;
+use player::PlayerSide;
+use vector2::Vector2;
+
+/// Encodings the tabular agent can learn with, recorded in its tables
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum EncoderType {
+    #[default]
+    Basic,
+    Full,
+}
+
+impl EncoderType {
+    pub fn encoder(self) -> &'static dyn StateEncoder {
+        match self {
+            EncoderType::Basic => &BasicEncoder,
+            EncoderType::Full => &FullEncoder,
+        }
+    }
+}
+
+pub fn encoder_type_from_name(name: &str) -> Option<EncoderType> {
+    match name {
+        "basic" => Some(EncoderType::Basic),
+        "full" => Some(EncoderType::Full),
+        _ => None,
+    }
+}
+
+/// Discretization of the state seen by a player into a row of a Q-table.
+///
+/// Each field of the state is a digit of a number in a mixed radix, the first field being the least significant.
+pub trait StateEncoder: Sync {
+    fn encoder_type(&self) -> EncoderType;
+
+    /// Changed along with the fields of the encoding, see `signature`
+    fn version(&self) -> u32;
+
+    /// Number of values of each field with the config of `engine`
+    fn field_sizes(&self, engine: &GameEngine) -> Vec<u32>;
+
+    /// State seen by `side`, on the court mirrored for the right player
+    fn encode(&self, engine: &GameEngine, side: PlayerSide) -> u64;
+
+    /// Name and value of each field of `state`, on the court of the left player.
+    /// Positions are the middle of the range of positions of the state.
+    fn decode(&self, engine: &GameEngine, state: u64) -> Vec<(&'static str, f64)>;
+
+    /// Number of distinct states with the config of `engine`
+    fn state_count(&self, engine: &GameEngine) -> u64 {
+        self.field_sizes(engine).iter().map(|size| *size as u64).product()
+    }
+
+    /// Identifies the encoding with the config of `engine`, so that tables trained with another one are rejected
+    fn signature(&self, engine: &GameEngine) -> u64 {
+        // FNV-1a over the encoder, its version and the number of values of each field
+        let mut signature: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut words = vec![self.encoder_type() as u32, self.version()];
+        words.extend(self.field_sizes(engine));
+        for word in words {
+            for byte in word.to_le_bytes().iter() {
+                signature ^= *byte as u64;
+                signature = signature.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        signature
+    }
+}
+
+/// Splits `state` into its fields
+fn digits(mut state: u64, sizes: &[u32]) -> Vec<u64> {
+    sizes.iter().map(|&size| {
+        let digit = state % size as u64;
+        state /= size as u64;
+        digit
+    }).collect()
+}
+
+/// Encoding of `GameEngine::hash`: the positions of the player and of the frisbee,
+/// who threw it last and the angle it flies at.
+///
+/// It is kept as it was when the first tables were trained: positions out of the court wrap around,
+/// the angle is the sine of the slope rather than its arctangent, and a still frisbee counts as flying steeply down.
+pub struct BasicEncoder;
+
+impl BasicEncoder {
+    fn encode_with_sizes(&self, engine: &GameEngine, side: PlayerSide) -> (u64, Vec<u32>) {
+        fn set_state(hash: &mut u64, val: f64, min: i64, max: i64, scale: f64, amplitudes: &mut Vec<u32>) {
+            fn discretize(val: f64, min: i64, max: i64, scale: f64) -> (u32, u32) {
+                let min = min as f64 * scale;
+                let max = max as f64 * scale;
+                let val = val * scale;
+                let amplitude = (max - min + 1.0) as u32;
+                let res = ((val.round() + min.abs()) as u32) % amplitude;
+                (res, amplitude)
+            }
+
+            let mut factor = 1;
+            for a in amplitudes.iter() {
+                factor *= *a;
+            }
+            let (val, amplitude) = discretize(val, min, max, scale);
+            *hash += (val * factor) as u64;
+            amplitudes.push(amplitude);
+        }
+
+        fn discretize_frisbee_direction(v: Vector2) -> f64 {
+            fn angle(v: Vector2) -> f64 {
+                (v.y / v.x.abs()).sin().to_degrees()
+            }
+
+            let a = angle(v);
+            if a > 40.0 {
+                0.0
+            } else if a > 10.0 {
+                1.0
+            } else if a < 10.0 && a > -10.0 {
+                2.0
+            } else if a < -10.0 && a > -40.0 {
+                3.0
+            } else {
+                4.0
+            }
+        }
+
+        let mut val = 0;
+        let mut amplitudes: Vec<u32> = Vec::new();
+
+        // Bounds of the court in whole units
+        let wall_ext = engine.config.wall_ext.floor() as i64;
+        let net = engine.config.net.ceil() as i64;
+        let wall_vertical = engine.config.wall_vertical.ceil() as i64;
+
+        let (player, mirror) = match side {
+            PlayerSide::Left => (&engine.players.0, 1.0),
+            PlayerSide::Right => (&engine.players.1, -1.0),
+        };
+
+        let scale = 1.0;
+        set_state(&mut val, player.pos.x * mirror, -wall_ext, -net, scale, &mut amplitudes);
+        set_state(&mut val, player.pos.y, -wall_vertical, wall_vertical, scale, &mut amplitudes);
+
+        set_state(&mut val, engine.frisbee.pos.x * mirror, -wall_ext, wall_ext, scale, &mut amplitudes);
+        set_state(&mut val, engine.frisbee.pos.y, -wall_vertical, wall_vertical, scale, &mut amplitudes);
+
+        set_state(&mut val, match engine.frisbee.last_held {
+            Some(held_by) if held_by == side => 1.0,
+            _ => 0.0
+        }, 0, 1, 1.0, &mut amplitudes);
+
+        set_state(&mut val, discretize_frisbee_direction(engine.frisbee.direction), 0, 4, 1.0, &mut amplitudes);
+
+        (val, amplitudes)
+    }
+}
+
+impl StateEncoder for BasicEncoder {
+    fn encoder_type(&self) -> EncoderType {
+        EncoderType::Basic
+    }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn field_sizes(&self, engine: &GameEngine) -> Vec<u32> {
+        self.encode_with_sizes(engine, PlayerSide::Left).1
+    }
+
+    fn encode(&self, engine: &GameEngine, side: PlayerSide) -> u64 {
+        self.encode_with_sizes(engine, side).0
+    }
+
+    fn decode(&self, engine: &GameEngine, state: u64) -> Vec<(&'static str, f64)> {
+        let wall_ext = engine.config.wall_ext.floor();
+        let wall_vertical = engine.config.wall_vertical.ceil();
+        let digits = digits(state, &self.field_sizes(engine));
+        vec![
+            ("player_x", digits[0] as f64 - wall_ext),
+            ("player_y", digits[1] as f64 - wall_vertical),
+            ("frisbee_x", digits[2] as f64 - wall_ext),
+            ("frisbee_y", digits[3] as f64 - wall_vertical),
+            ("own_throw", digits[4] as f64),
+            ("direction", digits[5] as f64),
+        ]
+    }
+}
+
+/// Range of a field split into steps
+struct Field {
+    min:  f64,
+    max:  f64,
+    step: f64,
+}
+
+impl Field {
+    fn size(&self) -> u32 {
+        ((self.max - self.min) / self.step).round() as u32 + 1
+    }
+
+    /// Closest step, values out of the range being clamped
+    fn index(&self, val: f64) -> u32 {
+        ((val.clamp(self.min, self.max) - self.min) / self.step).round() as u32
+    }
+
+    fn value(&self, index: u64) -> f64 {
+        self.min + index as f64 * self.step
+    }
+}
+
+/// Who holds the frisbee: the player, its opponent, nobody after a throw of the opponent, or nobody else
+const HOLDERS: u32 = 4;
+/// Angles of the flight of the frisbee, and still
+const DIRECTIONS: u32 = 6;
+
+/// Encoding of the positions of both players and of the frisbee, who holds it or threw it last,
+/// and the angle it flies at. The opponent is only located within 3 units.
+pub struct FullEncoder;
+
+impl FullEncoder {
+    /// Own position, the opponent's, then the frisbee's, on the court of the left player
+    fn fields(engine: &GameEngine) -> [Field; 6] {
+        let wall_ext = engine.config.wall_ext.floor();
+        let net = engine.config.net.ceil();
+        let wall_vertical = engine.config.wall_vertical.ceil();
+        [
+            Field { min: -wall_ext, max: -net, step: 1.0 },
+            Field { min: -wall_vertical, max: wall_vertical, step: 1.0 },
+            Field { min: net, max: wall_ext, step: 3.0 },
+            Field { min: -wall_vertical, max: wall_vertical, step: 3.0 },
+            Field { min: -wall_ext, max: wall_ext, step: 1.0 },
+            Field { min: -wall_vertical, max: wall_vertical, step: 1.0 },
+        ]
+    }
+
+    /// Angle of the flight towards either side, or still
+    fn direction(engine: &GameEngine) -> u32 {
+        let frisbee = &engine.frisbee;
+        if frisbee.held_by_player.is_some() || frisbee.speed == 0.0 {
+            return DIRECTIONS - 1;
+        }
+        let angle = frisbee.direction.y.atan2(frisbee.direction.x.abs()).to_degrees();
+        match angle {
+            a if a > 40.0 => 0,
+            a if a > 10.0 => 1,
+            a if a >= -10.0 => 2,
+            a if a >= -40.0 => 3,
+            _ => 4,
+        }
+    }
+}
+
+impl StateEncoder for FullEncoder {
+    fn encoder_type(&self) -> EncoderType {
+        EncoderType::Full
+    }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn field_sizes(&self, engine: &GameEngine) -> Vec<u32> {
+        let mut sizes: Vec<u32> = Self::fields(engine).iter().map(Field::size).collect();
+        sizes.push(HOLDERS);
+        sizes.push(DIRECTIONS);
+        sizes
+    }
+
+    fn encode(&self, engine: &GameEngine, side: PlayerSide) -> u64 {
+        let (own, other, mirror, opponent_side) = match side {
+            PlayerSide::Left => (&engine.players.0, &engine.players.1, 1.0, PlayerSide::Right),
+            PlayerSide::Right => (&engine.players.1, &engine.players.0, -1.0, PlayerSide::Left),
+        };
+        let frisbee = &engine.frisbee;
+        let holder = match frisbee.held_by_player {
+            Some(held_by) if held_by == side => 0,
+            Some(_) => 1,
+            None if frisbee.last_held == Some(opponent_side) => 2,
+            None => 3,
+        };
+
+        let fields = Self::fields(engine);
+        let values = [own.pos.x * mirror, own.pos.y, other.pos.x * mirror, other.pos.y, frisbee.pos.x * mirror, frisbee.pos.y];
+        let mut digits: Vec<(u32, u32)> = fields.iter().zip(values.iter()).map(|(field, &val)| (field.index(val), field.size())).collect();
+        digits.push((holder, HOLDERS));
+        digits.push((Self::direction(engine), DIRECTIONS));
+
+        let mut state = 0;
+        let mut factor = 1;
+        for (digit, size) in digits {
+            state += digit as u64 * factor;
+            factor *= size as u64;
+        }
+        state
+    }
+
+    fn decode(&self, engine: &GameEngine, state: u64) -> Vec<(&'static str, f64)> {
+        let digits = digits(state, &self.field_sizes(engine));
+        let names = ["player_x", "player_y", "opponent_x", "opponent_y", "frisbee_x", "frisbee_y"];
+        let mut decoded: Vec<(&'static str, f64)> = names.iter().zip(Self::fields(engine).iter()).zip(digits.iter())
+            .map(|((&name, field), &digit)| (name, field.value(digit)))
+            .collect();
+        decoded.push(("holder", digits[6] as f64));
+        decoded.push(("direction", digits[7] as f64));
+        decoded
+    }
+}
@@ -0,0 +1,279 @@
+use agent::QVALUES_ACTIONS;
+use game_engine::GameEngine;
+use player::PlayerSide;
+use q_table::{ ActionValues, StateValues, QValues };
+
+use rand::Rng;
+
+use std::collections::{ HashMap, VecDeque };
+use std::sync::Arc;
+
+/// Traces below this are dropped, so that only the last states of a point are updated
+const TRACE_MIN: f32 = 0.01;
+
+/// Update rule of the tabular agent
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum TdAlgorithm {
+    /// Bootstraps on the best action of the next state
+    #[default]
+    QLearning,
+    /// Bootstraps on the action taken in the next state
+    Sarsa,
+    /// Bootstraps on the mean value of the exploring policy in the next state
+    ExpectedSarsa,
+    /// Two tables, each one updated with the value the other gives to its own best action
+    DoubleQLearning,
+}
+
+pub fn td_algorithm_from_name(name: &str) -> Option<TdAlgorithm> {
+    match name {
+        "q-learning" => Some(TdAlgorithm::QLearning),
+        "sarsa" => Some(TdAlgorithm::Sarsa),
+        "expected-sarsa" => Some(TdAlgorithm::ExpectedSarsa),
+        "double-q" => Some(TdAlgorithm::DoubleQLearning),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TdConfig {
+    pub algorithm:     TdAlgorithm,
+    pub learning_rate: f32,
+    pub discount:      f32,
+    pub n_steps:       u32, // Rewards summed before bootstrapping, 1 for one-step updates
+    pub trace_decay:   f32, // Lambda of the eligibility traces, 0 for none
+}
+
+impl Default for TdConfig {
+    fn default() -> Self {
+        Self {
+            algorithm:     TdAlgorithm::QLearning,
+            learning_rate: 0.8,
+            discount:      0.95,
+            n_steps:       1,
+            trace_decay:   0.0,
+        }
+    }
+}
+
+/// Frame played by a learning side
+#[derive(Clone, Copy, Debug)]
+struct Step {
+    state:      u64,
+    action:     usize,
+    reward:     f32,
+    next_state: u64,
+    right_half: bool, // Of the table, see `QValues`
+}
+
+/// Frames of the current point of one side that are not updated yet
+#[derive(Clone, Debug, Default)]
+struct Trajectory {
+    steps:  VecDeque<Step>,
+    traces: HashMap<(u64, usize), f32>, // Eligibility of each state and action
+}
+
+fn half(values: &StateValues, right_half: bool) -> &ActionValues {
+    if right_half { &values.1 } else { &values.0 }
+}
+
+fn half_mut(values: &mut StateValues, right_half: bool) -> &mut ActionValues {
+    if right_half { &mut values.1 } else { &mut values.0 }
+}
+
+/// Values of `state`, zero if it was never visited
+fn action_values(table: &QValues, state: u64, right_half: bool) -> ActionValues {
+    table.get(state).map_or([0.0; QVALUES_ACTIONS], |values| *half(values, right_half))
+}
+
+/// Values of the first table of Double Q-learning, from the mean and the second one
+fn first_values(mean: &ActionValues, second: &ActionValues) -> ActionValues {
+    let mut first = [0.0; QVALUES_ACTIONS];
+    for (q, (m, s)) in first.iter_mut().zip(mean.iter().zip(second.iter())) {
+        *q = 2.0 * m - s;
+    }
+    first
+}
+
+fn max(values: &ActionValues) -> f32 {
+    values.iter().cloned().fold(f32::MIN, f32::max)
+}
+
+/// Updates the Q-values of the tabular agent from the frames it plays, with the rule of its `TdConfig`.
+///
+/// With Double Q-learning, the table of the engine holds the mean of both tables, which the agent plays greedily on,
+/// and the learner holds the second one, from which the first one is worked out.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TdLearner {
+    pub config:  TdConfig,
+    pub second:  Option<Arc<QValues>>, // Double Q-learning
+    #[serde(skip)]
+    trajectories: [Trajectory; 2],
+    #[serde(skip)]
+    error_sum:    f64,
+    #[serde(skip)]
+    updates:      u64,
+}
+
+impl TdLearner {
+    /// Learner of the values in `q_values`, whose storage the second table of Double Q-learning copies
+    pub fn new(config: TdConfig, q_values: &QValues) -> Self {
+        let second = match config.algorithm {
+            TdAlgorithm::DoubleQLearning => Some(Arc::new(match q_values {
+                QValues::Dense(values) => QValues::dense(values.len() as u64),
+                QValues::Sparse(_) => QValues::default(),
+            })),
+            _ => None,
+        };
+        Self {
+            config,
+            second,
+            trajectories: Default::default(),
+            error_sum:    0.0,
+            updates:      0,
+        }
+    }
+
+    /// Forgets the frames of the last point
+    pub fn start_point(&mut self) {
+        self.trajectories = Default::default();
+    }
+
+    /// Mean absolute TD error of the updates since the last call, to compare how the rules converge
+    pub fn take_mean_error(&mut self) -> Option<f64> {
+        let mean = if self.updates > 0 { Some(self.error_sum / self.updates as f64) } else { None };
+        self.error_sum = 0.0;
+        self.updates = 0;
+        mean
+    }
+
+    /// Records the frame `side` just played from `state` and updates the frames whose return is known.
+    /// `right_half` is the half of the table of `side`, see `QValues`.
+    pub fn observe(&mut self, engine: &mut GameEngine, side: PlayerSide, right_half: bool, state: u64, action: usize, reward: f32) {
+        let step = Step {
+            state,
+            action,
+            reward,
+            next_state: engine.q_state(side),
+            right_half,
+        };
+        let index = side as usize;
+        self.trajectories[index].steps.push_back(step);
+
+        if engine.q_scored {
+            // Nothing is left to expect after a goal
+            self.flush(engine, index, true);
+        } else {
+            // SARSA bootstraps on the action taken in the last state, only known on the next frame
+            let needed = match self.config.algorithm {
+                TdAlgorithm::Sarsa => self.config.n_steps as usize + 1,
+                _ => self.config.n_steps as usize,
+            };
+            if self.trajectories[index].steps.len() >= needed {
+                self.update_oldest(engine, index, false);
+            }
+        }
+    }
+
+    /// Updates the frames left when a point ends without a goal, bootstrapping on their last state.
+    /// SARSA does not know the next action there and bootstraps as Expected SARSA.
+    pub fn finish_point(&mut self, engine: &mut GameEngine) {
+        for index in 0..self.trajectories.len() {
+            self.flush(engine, index, false);
+        }
+    }
+
+    fn flush(&mut self, engine: &mut GameEngine, index: usize, terminal: bool) {
+        while !self.trajectories[index].steps.is_empty() {
+            self.update_oldest(engine, index, terminal);
+        }
+        self.trajectories[index].traces.clear();
+    }
+
+    /// Value of the next `state` in the target of an update, see `TdAlgorithm`
+    fn bootstrap(&self, q_values: &QValues, state: u64, action: Option<usize>, right_half: bool, update_second: bool, explo_rate: f32) -> f32 {
+        let values = action_values(q_values, state, right_half);
+        match (self.config.algorithm, action) {
+            (TdAlgorithm::QLearning, _) => max(&values),
+            (TdAlgorithm::Sarsa, Some(action)) => values[action],
+            (TdAlgorithm::Sarsa, None) | (TdAlgorithm::ExpectedSarsa, _) => {
+                // The agent picks a random action at the exploration rate, its greedy one otherwise
+                let average = values.iter().sum::<f32>() / QVALUES_ACTIONS as f32;
+                explo_rate * average + (1.0 - explo_rate) * max(&values)
+            },
+            (TdAlgorithm::DoubleQLearning, _) => {
+                let second = action_values(self.second.as_ref().expect("Double Q-learning without a second table"), state, right_half);
+                let first = first_values(&values, &second);
+                let (chooser, evaluator) = if update_second { (&second, &first) } else { (&first, &second) };
+                evaluator[::agent::greedy_action(chooser)]
+            },
+        }
+    }
+
+    /// Updates the oldest frame of a trajectory with the return of its next `n_steps` frames, and forgets it
+    fn update_oldest(&mut self, engine: &mut GameEngine, index: usize, terminal: bool) {
+        let n_steps = self.config.n_steps as usize;
+        let discount = self.config.discount;
+        let oldest = self.trajectories[index].steps[0];
+
+        let double = self.second.is_some();
+        let update_second = double && engine.agent_rng.gen::<bool>();
+        let q_values = Arc::make_mut(&mut engine.q_values);
+
+        let steps = &self.trajectories[index].steps;
+        let mut target = 0.0;
+        let mut factor = 1.0;
+        for step in steps.iter().take(n_steps) {
+            target += factor * step.reward;
+            factor *= discount;
+        }
+        if steps.len() > n_steps {
+            // Only with SARSA, which knows the action taken after the last rewarded frame
+            let next = steps[n_steps];
+            target += factor * self.bootstrap(q_values, next.state, Some(next.action), next.right_half, update_second, engine.explo_rate);
+        } else if !terminal {
+            let last = steps[steps.len() - 1];
+            target += factor * self.bootstrap(q_values, last.next_state, None, last.right_half, update_second, engine.explo_rate);
+        }
+
+        let mean = action_values(q_values, oldest.state, oldest.right_half);
+        let current = match self.second {
+            Some(ref second) => {
+                let second = action_values(second, oldest.state, oldest.right_half);
+                if update_second { second[oldest.action] } else { first_values(&mean, &second)[oldest.action] }
+            },
+            None => mean[oldest.action],
+        };
+        let delta = target - current;
+        self.error_sum += delta.abs() as f64;
+        self.updates += 1;
+
+        let learning_rate = self.config.learning_rate;
+        let trace_decay = self.config.trace_decay;
+        let mut second = if update_second { self.second.as_mut().map(Arc::make_mut) } else { None };
+        let trajectory = &mut self.trajectories[index];
+        let mut apply = |state: u64, action: usize, change: f32| {
+            if double {
+                // Each table moves the mean by half as much
+                half_mut(q_values.entry(state), oldest.right_half)[action] += change / 2.0;
+                if let Some(ref mut second) = second {
+                    half_mut(second.entry(state), oldest.right_half)[action] += change;
+                }
+            } else {
+                half_mut(q_values.entry(state), oldest.right_half)[action] += change;
+            }
+        };
+        if trace_decay > 0.0 {
+            // Replacing traces, which are not cut after exploring actions
+            trajectory.traces.insert((oldest.state, oldest.action), 1.0);
+            for (&(state, action), trace) in trajectory.traces.iter_mut() {
+                apply(state, action, learning_rate * delta * *trace);
+                *trace *= discount * trace_decay;
+            }
+            trajectory.traces.retain(|_, trace| *trace >= TRACE_MIN);
+        } else {
+            apply(oldest.state, oldest.action, learning_rate * delta);
+        }
+        trajectory.steps.pop_front();
+    }
+}
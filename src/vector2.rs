@@ -1,6 +1,6 @@
 use std::ops::{ Mul, MulAssign, Div, DivAssign, Add, AddAssign, Sub, SubAssign };
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Vector2 {
     pub x: f64,
     pub y: f64
@@ -28,16 +28,29 @@ impl Vector2 {
         let len = self.length();
         if len != 0.0 {
             assert_ne!(len, 0.0f64);
-            self.x = self.x / len;
-            self.y = self.y / len;
+            self.x /= len;
+            self.y /= len;
         }
     }
 
     pub fn normalized(&self) -> Self {
-        let mut cpy = self.clone();
+        let mut cpy = *self;
         cpy.normalize();
         cpy
     }
+
+    pub fn dot(&self, other: &Vector2) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// Rotates counterclockwise by `angle` radians
+    pub fn rotated(&self, angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            x: self.x * cos - self.y * sin,
+            y: self.x * sin + self.y * cos,
+        }
+    }
 }
 
 impl Mul for Vector2 {